@@ -0,0 +1,435 @@
+//! Random changes to [GrayImage28]s to make more training examples.
+//!
+//! Every [Transform] draws its random parameters from the rng it is given, so the same seed always gives the same images.
+//! Transforms are combined with [Pipeline], e.g.
+//! `Pipeline::new().then(RandomAffine::new().rotation(15.0).translation(2.0))`
+
+use crate::{DataSet, GrayImage28, IMAGE_HEIGHT, IMAGE_SIZE, IMAGE_WIDTH, subset::Subset};
+
+use core::fmt;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// A random change to an image
+pub trait Transform {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28;
+
+    /// Applies this transform with random parameters that only depend on `seed`
+    fn apply_seeded(&self, image: impl Into<GrayImage28>, seed: u64) -> GrayImage28
+    where
+        Self: Sized,
+    {
+        self.apply(image.into(), &mut ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T: Transform + ?Sized> Transform for Box<T> {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        T::apply(self, image, rng)
+    }
+}
+
+/// Applies its transforms in order
+#[derive(Default)]
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform + Send + Sync>>,
+}
+impl Pipeline {
+    /// Leaves images unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds `transform` after the others
+    pub fn then(mut self, transform: impl Transform + Send + Sync + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+}
+impl Transform for Pipeline {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        self.transforms
+            .iter()
+            .fold(image, |image, transform| transform.apply(image, rng))
+    }
+}
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("len", &self.transforms.len())
+            .finish()
+    }
+}
+
+/// Every example of `subset` with its image transformed. The images only depend on `seed` and the order of `subset`
+pub fn augmented<'a, D: DataSet>(
+    subset: &'a Subset<D>,
+    transform: &'a impl Transform,
+    seed: u64,
+) -> impl ExactSizeIterator<Item = (GrayImage28, D::Label)> + 'a {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    subset
+        .all_examples()
+        .map(move |(image, label)| (transform.apply(image.into(), &mut rng), label))
+}
+
+/// An affine map of the pixel coordinates around the center of the image.
+/// Pixels are sampled with bilinear interpolation and pixels from outside the image are background
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    matrix: [[f32; 2]; 2],
+    translation: [f32; 2],
+}
+impl Affine {
+    pub const IDENTITY: Self = Self {
+        matrix: [[1.0, 0.0], [0.0, 1.0]],
+        translation: [0.0, 0.0],
+    };
+    /// Counterclockwise by `degrees`
+    pub fn rotation(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            matrix: [[cos, sin], [-sin, cos]],
+            ..Self::IDENTITY
+        }
+    }
+    /// Moves the image `dx` pixels right and `dy` pixels down
+    pub fn translation(dx: f32, dy: f32) -> Self {
+        Self {
+            translation: [dx, dy],
+            ..Self::IDENTITY
+        }
+    }
+    /// Enlarges the image by `factor`
+    pub fn scale(factor: f32) -> Self {
+        Self {
+            matrix: [[factor, 0.0], [0.0, factor]],
+            ..Self::IDENTITY
+        }
+    }
+    /// Slants the image horizontally by `degrees`, moving the bottom right and the top left
+    pub fn shear(degrees: f32) -> Self {
+        Self {
+            matrix: [[1.0, degrees.to_radians().tan()], [0.0, 1.0]],
+            ..Self::IDENTITY
+        }
+    }
+    /// `self` followed by `next`
+    pub fn then(self, next: Self) -> Self {
+        let [[a, b], [c, d]] = next.matrix;
+        let [[e, f], [g, h]] = self.matrix;
+        let (tx, ty) = next.map_point(self.translation[0], self.translation[1]);
+        Self {
+            matrix: [
+                [a * e + b * g, a * f + b * h],
+                [c * e + d * g, c * f + d * h],
+            ],
+            translation: [tx, ty],
+        }
+    }
+
+    /// Returns [None] if the map isn't invertible
+    fn inverse(&self) -> Option<Self> {
+        let [[a, b], [c, d]] = self.matrix;
+        let determinant = a * d - b * c;
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let matrix = [[d, -b], [-c, a]].map(|row| row.map(|element| element / determinant));
+        let [tx, ty] = self.translation;
+        Some(Self {
+            matrix,
+            translation: [
+                -(matrix[0][0] * tx + matrix[0][1] * ty),
+                -(matrix[1][0] * tx + matrix[1][1] * ty),
+            ],
+        })
+    }
+    fn map_point(&self, x: f32, y: f32) -> (f32, f32) {
+        let [[a, b], [c, d]] = self.matrix;
+        let [tx, ty] = self.translation;
+        (a * x + b * y + tx, c * x + d * y + ty)
+    }
+
+    /// A blank image if the map isn't invertible
+    pub fn transform(&self, image: &GrayImage28) -> GrayImage28 {
+        let Some(inverse) = self.inverse() else {
+            return GrayImage28::blank();
+        };
+        let (center_x, center_y) = CENTER;
+        GrayImage28::from_fn(|x, y| {
+            let (source_x, source_y) = inverse.map_point(x as f32 - center_x, y as f32 - center_y);
+            to_byte(sample(image, source_x + center_x, source_y + center_y))
+        })
+    }
+}
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+impl Transform for Affine {
+    fn apply(&self, image: GrayImage28, _: &mut ChaCha8Rng) -> GrayImage28 {
+        self.transform(&image)
+    }
+}
+
+/// An [Affine] map with random parameters, each drawn uniformly. It scales, shears, rotates and then translates.
+/// Every parameter leaves the image unchanged by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomAffine {
+    rotation: f32,
+    translation: f32,
+    scale: (f32, f32),
+    shear: f32,
+}
+impl RandomAffine {
+    pub const fn new() -> Self {
+        Self {
+            rotation: 0.0,
+            translation: 0.0,
+            scale: (1.0, 1.0),
+            shear: 0.0,
+        }
+    }
+    /// Rotates by up to `degrees` either way
+    pub const fn rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees.abs();
+        self
+    }
+    /// Translates by up to `pixels` in each direction, including fractions of a pixel
+    pub const fn translation(mut self, pixels: f32) -> Self {
+        self.translation = pixels.abs();
+        self
+    }
+    /// Scales by a factor between `min` and `max`
+    ///
+    /// # Panics
+    /// if `min > max`
+    pub const fn scale(mut self, min: f32, max: f32) -> Self {
+        assert!(
+            min <= max,
+            "the minimum scale must not be larger than the maximum"
+        );
+        self.scale = (min, max);
+        self
+    }
+    /// Shears by up to `degrees` either way, see [Affine::shear]
+    pub const fn shear(mut self, degrees: f32) -> Self {
+        self.shear = degrees.abs();
+        self
+    }
+
+    /// The map of one random draw
+    pub fn sample(&self, rng: &mut ChaCha8Rng) -> Affine {
+        let scale = rng.random_range(self.scale.0..=self.scale.1);
+        let shear = rng.random_range(-self.shear..=self.shear);
+        let rotation = rng.random_range(-self.rotation..=self.rotation);
+        let dx = rng.random_range(-self.translation..=self.translation);
+        let dy = rng.random_range(-self.translation..=self.translation);
+        Affine::scale(scale)
+            .then(Affine::shear(shear))
+            .then(Affine::rotation(rotation))
+            .then(Affine::translation(dx, dy))
+    }
+}
+impl Default for RandomAffine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Transform for RandomAffine {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        self.sample(rng).transform(&image)
+    }
+}
+
+/// Moves every pixel by a random displacement field that is smoothed by a Gaussian with standard deviation `sigma`
+/// and scaled by `alpha`, as in Simard et al., "Best Practices for Convolutional Neural Networks Applied to Visual Document Analysis".
+/// `Elastic::new(34.0, 4.0)` is close to the paper
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elastic {
+    alpha: f32,
+    sigma: f32,
+}
+impl Elastic {
+    /// Larger `alpha` moves pixels further, larger `sigma` moves neighboring pixels more alike
+    ///
+    /// # Panics
+    /// if `sigma` is negative or either isn't finite
+    pub fn new(alpha: f32, sigma: f32) -> Self {
+        assert!(
+            alpha.is_finite() && sigma.is_finite() && sigma >= 0.0,
+            "sigma must not be negative and both must be finite"
+        );
+        Self { alpha, sigma }
+    }
+    /// The displacements `(dx, dy)` of every pixel in row major order
+    pub fn displacement_field(
+        &self,
+        rng: &mut ChaCha8Rng,
+    ) -> ([f32; IMAGE_SIZE], [f32; IMAGE_SIZE]) {
+        let mut field = || {
+            let noise = core::array::from_fn(|_| rng.random_range(-1.0..=1.0));
+            gaussian_blur(&noise, self.sigma).map(|displacement| displacement * self.alpha)
+        };
+        (field(), field())
+    }
+}
+impl Transform for Elastic {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        let (dx, dy) = self.displacement_field(rng);
+        GrayImage28::from_fn(|x, y| {
+            let i = y * IMAGE_WIDTH + x;
+            to_byte(sample(&image, x as f32 + dx[i], y as f32 + dy[i]))
+        })
+    }
+}
+
+/// Convolves a row major image with a Gaussian kernel, repeating the edge values outside the image
+pub(crate) fn gaussian_blur(values: &[f32; IMAGE_SIZE], sigma: f32) -> [f32; IMAGE_SIZE] {
+    if sigma == 0.0 {
+        return *values;
+    }
+    let radius = (3.0 * sigma).ceil() as isize;
+    let kernel = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let total = kernel.iter().sum::<f32>();
+    let convolve = |get: &dyn Fn(isize) -> f32| {
+        (-radius..=radius)
+            .zip(&kernel)
+            .map(|(offset, weight)| get(offset) * weight)
+            .sum::<f32>()
+            / total
+    };
+    let clamp = |i: isize, length: usize| i.clamp(0, length as isize - 1) as usize;
+    let rows: [f32; IMAGE_SIZE] = core::array::from_fn(|i| {
+        let (x, y) = (i % IMAGE_WIDTH, i / IMAGE_WIDTH);
+        convolve(&|offset| values[y * IMAGE_WIDTH + clamp(x as isize + offset, IMAGE_WIDTH)])
+    });
+    core::array::from_fn(|i| {
+        let (x, y) = (i % IMAGE_WIDTH, i / IMAGE_WIDTH);
+        convolve(&|offset| rows[clamp(y as isize + offset, IMAGE_HEIGHT) * IMAGE_WIDTH + x])
+    })
+}
+
+/// The point that rotations, scaling and shear keep in place
+const CENTER: (f32, f32) = (
+    (IMAGE_WIDTH - 1) as f32 / 2.0,
+    (IMAGE_HEIGHT - 1) as f32 / 2.0,
+);
+
+/// Bilinear interpolation of the pixels around `(x, y)`, pixels outside the image are `0`
+pub(crate) fn sample(image: &GrayImage28, x: f32, y: f32) -> f32 {
+    let (left, top) = (x.floor(), y.floor());
+    let (dx, dy) = (x - left, y - top);
+    let pixel = |x: f32, y: f32| {
+        if x < 0.0 || y < 0.0 {
+            return 0.0;
+        }
+        image.get(x as usize, y as usize).map_or(0.0, f32::from)
+    };
+    let top_row = pixel(left, top) * (1.0 - dx) + pixel(left + 1.0, top) * dx;
+    let bottom_row = pixel(left, top + 1.0) * (1.0 - dx) + pixel(left + 1.0, top + 1.0) * dx;
+    top_row * (1.0 - dy) + bottom_row * dy
+}
+
+pub(crate) fn to_byte(value: f32) -> u8 {
+    value.round().clamp(0.0, u8::MAX as f32) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        GrayImage28,
+        augmentation::{Affine, Elastic, Pipeline, RandomAffine, Transform, gaussian_blur},
+        image::{IMAGE_SIZE, IMAGE_WIDTH},
+    };
+
+    fn dot(x: usize, y: usize) -> GrayImage28 {
+        let mut image = GrayImage28::blank();
+        *image.get_mut(x, y).unwrap() = 200;
+        image
+    }
+
+    #[test]
+    fn affine_maps_move_pixels() {
+        assert_eq!(Affine::IDENTITY.transform(&dot(3, 4)), dot(3, 4));
+        assert_eq!(
+            Affine::translation(2.0, -1.0).transform(&dot(3, 4)),
+            dot(5, 3)
+        );
+        // (17, 13) is 3.5 right of the center and ends up 3.5 above it
+        assert_eq!(Affine::rotation(90.0).transform(&dot(17, 13)), dot(13, 10));
+        assert_eq!(
+            Affine::scale(3.0).transform(&dot(14, 14)).get(15, 15),
+            Some(200)
+        );
+
+        let half = Affine::translation(0.5, 0.0).transform(&dot(3, 4));
+        assert_eq!((half.get(3, 4), half.get(4, 4)), (Some(100), Some(100)));
+
+        let composed = Affine::rotation(90.0).then(Affine::translation(1.0, 0.0));
+        assert_eq!(composed.transform(&dot(17, 13)), dot(14, 10));
+        assert_eq!(
+            Affine::scale(0.0).transform(&dot(3, 4)),
+            GrayImage28::blank()
+        );
+    }
+
+    #[test]
+    fn pipelines_are_seeded() {
+        let image = GrayImage28::from_fn(|x, y| (x * 9 + y) as u8);
+        let pipeline = Pipeline::new()
+            .then(RandomAffine::new().rotation(20.0).scale(0.9, 1.1))
+            .then(RandomAffine::new().translation(2.0).shear(10.0));
+        let augmented = pipeline.apply_seeded(image, 7);
+        assert_ne!(augmented, image);
+        assert_eq!(pipeline.apply_seeded(image, 7), augmented);
+        assert_ne!(pipeline.apply_seeded(image, 8), augmented);
+        assert_eq!(RandomAffine::new().apply_seeded(image, 7), image);
+        assert_eq!(Pipeline::new().apply_seeded(image, 7), image);
+    }
+
+    #[test]
+    fn elastic_distortion_is_smooth_and_seeded() {
+        let image = GrayImage28::from_fn(|x, y| {
+            if (8..20).contains(&x) && y > 4 {
+                255
+            } else {
+                0
+            }
+        });
+        let elastic = Elastic::new(34.0, 4.0);
+        let distorted = elastic.apply_seeded(image, 7);
+        assert_ne!(distorted, image);
+        assert_eq!(elastic.apply_seeded(image, 7), distorted);
+        assert_ne!(elastic.apply_seeded(image, 8), distorted);
+        assert_eq!(Elastic::new(0.0, 4.0).apply_seeded(image, 7), image);
+
+        let (dx, _) = elastic.displacement_field(&mut rand::SeedableRng::seed_from_u64(7));
+        let largest_step = (1..IMAGE_SIZE)
+            .filter(|i| i % IMAGE_WIDTH != 0)
+            .map(|i| (dx[i] - dx[i - 1]).abs())
+            .fold(0.0, f32::max);
+        // neighbors of an unsmoothed field could be up to `2 * alpha` apart
+        assert!(largest_step < 34.0 / 10.0, "{largest_step}");
+    }
+
+    #[test]
+    fn gaussian_blur_keeps_constants_and_mass() {
+        assert_eq!(gaussian_blur(&[0.5; IMAGE_SIZE], 2.0), [0.5; IMAGE_SIZE]);
+        let mut dot = [0.0; IMAGE_SIZE];
+        dot[14 * 28 + 14] = 1.0;
+        let blurred = gaussian_blur(&dot, 1.0);
+        assert!((blurred.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(blurred[14 * 28 + 14] > blurred[14 * 28 + 15]);
+        assert_eq!(gaussian_blur(&dot, 0.0), dot);
+    }
+}
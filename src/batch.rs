@@ -0,0 +1,147 @@
+//! Mini-batches of a [DataSet] as [ndarray] matrices with one column per example
+
+use crate::{ClassSet, DataSet, GrayImage28, IMAGE_SIZE, Label, subset::Subset};
+
+use core::fmt;
+use ndarray::{Array2, ArrayView1};
+
+/// Yields `(inputs, outputs)` for every batch of a [Subset], in the order of the subset:
+/// - `inputs`: Shape = `(IMAGE_SIZE, batch_size)`, normalized pixels
+/// - `outputs`: Shape = `(<D::Label as Label>::Class::COUNT, batch_size)`, one hot encoded classes
+///
+/// The last batch is smaller if the subset doesn't divide evenly, unless [Batches::drop_last] is set
+pub struct Batches<D> {
+    subset: Subset<D>,
+    batch_size: usize,
+    drop_last: bool,
+    next: usize,
+}
+impl<D: DataSet> Batches<D> {
+    /// # Panics
+    /// if `batch_size == 0`
+    pub fn new(subset: Subset<D>, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batches must not be empty");
+        Self {
+            subset,
+            batch_size,
+            drop_last: false,
+            next: 0,
+        }
+    }
+    /// Whether to skip the last batch if it's smaller than the batch size
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+    /// Shuffles the examples before batching, see [Subset::shuffle]
+    pub fn shuffled(mut self, seed: u64) -> Self {
+        self.subset.shuffle(seed);
+        self
+    }
+
+    fn remaining(&self) -> usize {
+        let remaining = self.subset.len() - self.next;
+        if self.drop_last {
+            remaining / self.batch_size
+        } else {
+            remaining.div_ceil(self.batch_size)
+        }
+    }
+}
+impl<D: DataSet> Iterator for Batches<D> {
+    type Item = (Array2<f32>, Array2<f32>);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining() == 0 {
+            return None;
+        }
+        let size = self.batch_size.min(self.subset.len() - self.next);
+        let examples = self.subset.indices()[self.next..self.next + size]
+            .iter()
+            .map(|&index| D::get(index).expect("subsets only contain valid indices"))
+            .map(|(image, label)| (image, label.class()));
+        self.next += size;
+        Some(batch(examples))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+impl<D: DataSet> ExactSizeIterator for Batches<D> {}
+
+/// One batch of `(image, class)` pairs in the same shape as [Batches], e.g. of augmented [GrayImage28]s:
+/// - `inputs`: Shape = `(IMAGE_SIZE, batch_size)`, normalized pixels
+/// - `outputs`: Shape = `(C::COUNT, batch_size)`, one hot encoded classes
+pub fn batch<C: ClassSet>(
+    examples: impl IntoIterator<Item = (impl Into<GrayImage28>, C)>,
+) -> (Array2<f32>, Array2<f32>) {
+    let (images, classes) = examples
+        .into_iter()
+        .map(|(image, class)| (image.into(), class))
+        .collect::<(Vec<GrayImage28>, Vec<C>)>();
+    let mut inputs = Array2::zeros((IMAGE_SIZE, images.len()));
+    let mut outputs = Array2::zeros((C::COUNT, classes.len()));
+    for (column, (image, class)) in images.iter().zip(classes).enumerate() {
+        inputs
+            .column_mut(column)
+            .assign(&ArrayView1::from(&image.normalized()));
+        outputs[[class.index(), column]] = 1.0;
+    }
+    (inputs, outputs)
+}
+impl<D> fmt::Debug for Batches<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batches")
+            .field("subset", &self.subset)
+            .field("batch_size", &self.batch_size)
+            .field("drop_last", &self.drop_last)
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DataSet, DigitClass, GrayImage28, IMAGE_SIZE, Image, Label, TestData, TestImage, TestLabel,
+        batch::{Batches, batch},
+        subset::Subset,
+    };
+
+    #[test]
+    fn batches_are_columns() {
+        let batches = TestData::batches(64).collect::<Vec<_>>();
+        assert_eq!(batches.len(), TestImage::COUNT.div_ceil(64));
+        let (inputs, outputs) = batches.last().unwrap();
+        assert_eq!(inputs.dim(), (IMAGE_SIZE, TestImage::COUNT % 64));
+        assert_eq!(outputs.dim(), (DigitClass::COUNT, TestImage::COUNT % 64));
+
+        let (inputs, outputs) = &batches[1];
+        let image = TestImage::from_index(64).unwrap();
+        assert_eq!(inputs[[300, 0]], image.as_bytes()[300] as f32 / 255.0);
+        let class = TestLabel::from_index(64).unwrap().digit_class();
+        assert_eq!(outputs.column(0).to_vec(), class.one_hot_encode());
+
+        assert_eq!(
+            TestData::batches(64).drop_last(true).count(),
+            TestImage::COUNT / 64
+        );
+        let subset = Subset::<TestData>::from_indices([3, 1, 2]).unwrap();
+        assert_eq!(Batches::new(subset, 2).drop_last(true).len(), 1);
+    }
+
+    #[test]
+    fn owned_images_batch_like_handles() {
+        let examples = [(
+            GrayImage28::from_fn(|x, _| if x == 5 { 255 } else { 0 }),
+            DigitClass::One,
+        )];
+        let (inputs, outputs) = batch(examples);
+        assert_eq!(inputs.dim(), (IMAGE_SIZE, 1));
+        assert_eq!(
+            (inputs[[5, 0]], inputs[[6, 0]], inputs[[33, 0]]),
+            (1.0, 0.0, 1.0)
+        );
+        assert_eq!(outputs.column(0).to_vec(), DigitClass::One.one_hot_encode());
+    }
+}
@@ -1,6 +1,12 @@
 use crate::*;
 
-use core::marker::PhantomData;
+use crate::augmentation::{Pipeline, Transform};
+
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use std::sync::Arc;
 
 use burn::{data::dataloader::batcher::Batcher, prelude::*};
 
@@ -16,11 +22,51 @@ pub struct MnistBatch<B: Backend> {
     pub labels: Tensor<B, 1, Int>,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct MnistBatcher<D: DataSet>(PhantomData<D>);
+/// Turns `(image, label)` pairs into a [MnistBatch].
+/// With [MnistBatcher::with_augmentation] every image is augmented on the fly, differently for every batch
+///
+/// Not `Copy` because its clones share the augmentation, clone it to use it in several dataloaders
+#[derive(Debug)]
+pub struct MnistBatcher<D: DataSet> {
+    augmentation: Option<Arc<Augmentation>>,
+    data_set: PhantomData<D>,
+}
+#[derive(Debug)]
+struct Augmentation {
+    pipeline: Pipeline,
+    seed: u64,
+    batches: AtomicU64,
+}
 impl<D: DataSet> MnistBatcher<D> {
     pub const fn new() -> Self {
-        Self(PhantomData)
+        Self {
+            augmentation: None,
+            data_set: PhantomData,
+        }
+    }
+    /// Applies `pipeline` to every image. Each batch gets its own random parameters from `seed` and the number of batches
+    /// made so far by this batcher and its clones, so every epoch is augmented differently.
+    /// The augmentation is only reproducible if the batches are made in the same order, e.g. with a single worker
+    pub fn with_augmentation(mut self, pipeline: Pipeline, seed: u64) -> Self {
+        self.augmentation = Some(Arc::new(Augmentation {
+            pipeline,
+            seed,
+            batches: AtomicU64::new(0),
+        }));
+        self
+    }
+}
+impl<D: DataSet> Default for MnistBatcher<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<D: DataSet> Clone for MnistBatcher<D> {
+    fn clone(&self) -> Self {
+        Self {
+            augmentation: self.augmentation.clone(),
+            data_set: PhantomData,
+        }
     }
 }
 impl<B, D> Batcher<B, (D::Image, D::Label), MnistBatch<B>> for MnistBatcher<D>
@@ -33,16 +79,27 @@ where
         items: Vec<(D::Image, D::Label)>,
         device: &<B as Backend>::Device,
     ) -> MnistBatch<B> {
+        let mut augment = self.augmentation.as_deref().map(|augmentation| {
+            let batch = augmentation.batches.fetch_add(1, Ordering::Relaxed);
+            let mut rng = subset::seeded_rng(augmentation.seed, batch);
+            move |image| augmentation.pipeline.apply(image, &mut rng)
+        });
         let images = items
             .iter()
-            .map(|(image, _)| image.to_array())
-            .map(|data| Tensor::<B, 2>::from_data(data, device))
-            .map(|tensor| tensor.reshape([1, 28, 28]))
+            .map(|(image, _)| GrayImage28::new(image.to_array()))
+            .map(|image| match &mut augment {
+                Some(augment) => augment(image),
+                None => image,
+            })
+            .map(|image| {
+                TensorData::new(image.into_bytes().to_vec(), [1, IMAGE_HEIGHT, IMAGE_WIDTH])
+            })
+            .map(|data| Tensor::<B, 3>::from_data(data, device))
             .collect();
 
         let labels = items
             .iter()
-            .map(|(_, label)| [(label.digit_class() as i64).elem::<B::IntElem>()])
+            .map(|(_, label)| [(label.class().index() as i64).elem::<B::IntElem>()])
             .map(|data| Tensor::<B, 1, Int>::from_data(data, device))
             .collect();
 
@@ -59,6 +116,11 @@ impl<D: DataSet> MnistDataset<D> {
         Self(PhantomData)
     }
 }
+impl<D: DataSet> Default for MnistDataset<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl<D> burn::data::dataloader::Dataset<(D::Image, D::Label)> for MnistDataset<D>
 where
     D: DataSet + Send + Sync,
@@ -66,10 +128,91 @@ where
     D::Label: Send + Sync + Copy,
 {
     fn get(&self, i: usize) -> Option<(D::Image, D::Label)> {
-        D::Image::from_index(i)
-            .and_then(|image| D::Label::from_index(i).map(|label| (image, label)))
+        D::get(i)
     }
     fn len(&self) -> usize {
-        D::COUNT
+        D::count()
+    }
+}
+impl<D> burn::data::dataloader::Dataset<(D::Image, D::Label)> for subset::Subset<D>
+where
+    D: DataSet + Send + Sync,
+    D::Image: Send + Sync + Copy,
+    D::Label: Send + Sync + Copy,
+{
+    fn get(&self, i: usize) -> Option<(D::Image, D::Label)> {
+        subset::Subset::get(self, i)
+    }
+    fn len(&self) -> usize {
+        subset::Subset::len(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DataSet, IMAGE_HEIGHT, IMAGE_WIDTH, SourceData,
+        augmentation::Pipeline,
+        burn_interop::{MnistBatch, MnistBatcher},
+        corruption::GaussianNoise,
+        files::Training,
+        test_util::in_memory_source,
+    };
+
+    use burn::{backend::NdArray, data::dataloader::batcher::Batcher};
+
+    in_memory_source!(
+        /// One example of each class
+        TenDigits,
+        |_| 1
+    );
+    type Data = SourceData<TenDigits, Training>;
+
+    fn batch(batcher: &MnistBatcher<Data>) -> MnistBatch<NdArray> {
+        batcher.batch(Data::all().collect(), &Default::default())
+    }
+
+    #[test]
+    fn batches_images_and_labels() {
+        TenDigits::install();
+        let MnistBatch { images, labels } = batch(&MnistBatcher::new());
+        assert_eq!(images.dims(), [10, IMAGE_HEIGHT, IMAGE_WIDTH]);
+        assert_eq!(
+            labels.into_data().to_vec::<i64>().unwrap(),
+            (0..10).collect::<Vec<_>>()
+        );
+        let pixels = images.into_data().to_vec::<f32>().unwrap();
+        assert!(
+            pixels
+                .chunks_exact(IMAGE_HEIGHT * IMAGE_WIDTH)
+                .enumerate()
+                .all(|(class, image)| image.iter().all(|&pixel| pixel == class as f32))
+        );
+    }
+
+    #[test]
+    fn input_output_tensors_are_well_formed() {
+        TenDigits::install();
+        let (image, label) = Data::input_output_tensors::<NdArray>(&Default::default())
+            .nth(3)
+            .unwrap();
+        assert_eq!(image.dims(), [IMAGE_HEIGHT, IMAGE_WIDTH]);
+        assert_eq!(image.into_data().to_vec::<f32>().unwrap()[0], 3.0);
+        assert_eq!(label.into_data().to_vec::<f32>().unwrap(), [3.0]);
+    }
+
+    #[test]
+    fn augments_every_batch_differently() {
+        TenDigits::install();
+        let plain = batch(&MnistBatcher::new()).images.into_data();
+        let batcher = MnistBatcher::new()
+            .with_augmentation(Pipeline::new().then(GaussianNoise { std_dev: 0.2 }), 7);
+        let MnistBatch { images, labels } = batch(&batcher);
+        assert_eq!(images.dims(), [10, IMAGE_HEIGHT, IMAGE_WIDTH]);
+        assert_eq!(labels.dims(), [10]);
+        let first = images.into_data();
+        let second = batch(&batcher.clone()).images.into_data();
+        assert_ne!(first, plain);
+        assert_ne!(first, second);
     }
 }
@@ -0,0 +1,285 @@
+//! Corrupted images for measuring how a classifier degrades, similar to MNIST-C.
+//!
+//! Every corruption is a [Transform], so it can also be used for augmentation. [Corruption] picks the parameters of
+//! each corruption for a severity from `1` to `5`, and [corrupted] makes a corrupted copy of a [DataSet],
+//! e.g. `corrupted::<TestData>(Corruption::GaussianNoise, 3, seed)`
+
+use crate::{
+    DataSet, GrayImage28, IMAGE_HEIGHT, IMAGE_SIZE, IMAGE_WIDTH,
+    augmentation::{Transform, augmented, gaussian_blur, to_byte},
+    subset::Subset,
+};
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+/// Adds normally distributed noise with a standard deviation of `std_dev * 255` to every pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GaussianNoise {
+    pub std_dev: f32,
+}
+impl Transform for GaussianNoise {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        image.map(|byte| {
+            // Box-Muller transform
+            let (u1, u2) = (1.0 - rng.random::<f32>(), rng.random::<f32>());
+            let normal = (-2.0 * u1.ln()).sqrt() * (core::f32::consts::TAU * u2).cos();
+            to_byte(byte as f32 + normal * self.std_dev * u8::MAX as f32)
+        })
+    }
+}
+
+/// Sets each pixel to black or white with probability `amount / 2` each
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SaltAndPepper {
+    pub amount: f32,
+}
+impl Transform for SaltAndPepper {
+    fn apply(&self, image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        image.map(|byte| {
+            if rng.random::<f32>() >= self.amount {
+                byte
+            } else if rng.random() {
+                u8::MAX
+            } else {
+                0
+            }
+        })
+    }
+}
+
+/// Fills a `size x size` square at a random position inside the image with `value`, also known as cutout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erasing {
+    pub size: usize,
+    pub value: u8,
+}
+impl Transform for Erasing {
+    fn apply(&self, mut image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        let size = self.size.min(IMAGE_WIDTH).min(IMAGE_HEIGHT);
+        let left = rng.random_range(0..=IMAGE_WIDTH - size);
+        let top = rng.random_range(0..=IMAGE_HEIGHT - size);
+        for y in top..top + size {
+            for x in left..left + size {
+                *image.get_mut(x, y).expect("the square is inside the image") = self.value;
+            }
+        }
+        image
+    }
+}
+
+/// Gaussian blur with standard deviation `sigma` in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blur {
+    pub sigma: f32,
+}
+impl Transform for Blur {
+    fn apply(&self, image: GrayImage28, _: &mut ChaCha8Rng) -> GrayImage28 {
+        let pixels = image.as_bytes().map(f32::from);
+        GrayImage28::new(gaussian_blur(&pixels, self.sigma).map(to_byte))
+    }
+}
+
+/// Draws `count` white lines between random points of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scratches {
+    pub count: usize,
+}
+impl Transform for Scratches {
+    fn apply(&self, mut image: GrayImage28, rng: &mut ChaCha8Rng) -> GrayImage28 {
+        let mut point = || {
+            (
+                rng.random_range(0.0..IMAGE_WIDTH as f32),
+                rng.random_range(0.0..IMAGE_HEIGHT as f32),
+            )
+        };
+        for _ in 0..self.count {
+            let ((x0, y0), (x1, y1)) = (point(), point());
+            let steps = (2.0 * (x1 - x0).abs().max((y1 - y0).abs())).ceil() as usize + 1;
+            for step in 0..=steps {
+                let t = step as f32 / steps as f32;
+                let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                if let Some(pixel) = image.get_mut(x as usize, y as usize) {
+                    *pixel = u8::MAX;
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Adds `offset * 255` to every pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Brightness {
+    pub offset: f32,
+}
+impl Transform for Brightness {
+    fn apply(&self, image: GrayImage28, _: &mut ChaCha8Rng) -> GrayImage28 {
+        image.map(|byte| to_byte(byte as f32 + self.offset * u8::MAX as f32))
+    }
+}
+
+/// Scales the distance of every pixel from the mean pixel by `factor`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contrast {
+    pub factor: f32,
+}
+impl Transform for Contrast {
+    fn apply(&self, image: GrayImage28, _: &mut ChaCha8Rng) -> GrayImage28 {
+        let mean = image
+            .as_bytes()
+            .iter()
+            .map(|&byte| byte as f32)
+            .sum::<f32>()
+            / IMAGE_SIZE as f32;
+        image.map(|byte| to_byte(mean + (byte as f32 - mean) * self.factor))
+    }
+}
+
+/// A kind of corruption, see [Corruption::transform] for the parameters of each severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corruption {
+    GaussianNoise,
+    SaltAndPepper,
+    Erasing,
+    Blur,
+    Scratches,
+    Brightness,
+    Contrast,
+}
+impl Corruption {
+    pub const ALL: [Self; 7] = [
+        Self::GaussianNoise,
+        Self::SaltAndPepper,
+        Self::Erasing,
+        Self::Blur,
+        Self::Scratches,
+        Self::Brightness,
+        Self::Contrast,
+    ];
+    /// The highest severity
+    pub const MAX_SEVERITY: u8 = 5;
+
+    /// The transform of this corruption at `severity`, from `1` (mild) to [Corruption::MAX_SEVERITY]
+    ///
+    /// # Panics
+    /// if `severity` is `0` or larger than [Corruption::MAX_SEVERITY]
+    pub fn transform(self, severity: u8) -> Box<dyn Transform + Send + Sync> {
+        assert!(
+            (1..=Self::MAX_SEVERITY).contains(&severity),
+            "severity must be between 1 and {}",
+            Self::MAX_SEVERITY
+        );
+        let level = severity as usize - 1;
+        match self {
+            Self::GaussianNoise => Box::new(GaussianNoise {
+                std_dev: [0.08, 0.12, 0.18, 0.26, 0.38][level],
+            }),
+            Self::SaltAndPepper => Box::new(SaltAndPepper {
+                amount: [0.02, 0.04, 0.08, 0.12, 0.2][level],
+            }),
+            Self::Erasing => Box::new(Erasing {
+                size: [6, 9, 12, 15, 18][level],
+                value: 0,
+            }),
+            Self::Blur => Box::new(Blur {
+                sigma: [0.5, 0.75, 1.0, 1.5, 2.0][level],
+            }),
+            Self::Scratches => Box::new(Scratches {
+                count: [1, 2, 3, 4, 5][level],
+            }),
+            Self::Brightness => Box::new(Brightness {
+                offset: [0.1, 0.2, 0.3, 0.4, 0.5][level],
+            }),
+            Self::Contrast => Box::new(Contrast {
+                factor: [0.6, 0.45, 0.3, 0.2, 0.1][level],
+            }),
+        }
+    }
+}
+
+/// Every example of `D` with `corruption` at `severity` applied to its image. The images only depend on `seed`
+///
+/// # Panics
+/// see [Corruption::transform]
+pub fn corrupted<D: DataSet>(
+    corruption: Corruption,
+    severity: u8,
+    seed: u64,
+) -> Vec<(GrayImage28, D::Label)> {
+    let transform = corruption.transform(severity);
+    augmented(&Subset::<D>::all(), &transform, seed).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        GrayImage28, Image, Label, TestData, TestImage, TestLabel,
+        augmentation::Transform,
+        corruption::{Contrast, Corruption, Erasing, corrupted},
+    };
+
+    /// Large enough that every erased square covers part of it
+    fn digit() -> GrayImage28 {
+        GrayImage28::from_fn(|x, y| {
+            if (4..24).contains(&x) && (4..24).contains(&y) {
+                255
+            } else {
+                0
+            }
+        })
+    }
+
+    #[test]
+    fn corruptions_get_stronger() {
+        let distance = |a: &GrayImage28, b: &GrayImage28| {
+            a.as_bytes()
+                .iter()
+                .zip(b.as_bytes())
+                .map(|(&a, &b)| a.abs_diff(b) as u32)
+                .sum::<u32>()
+        };
+        for corruption in Corruption::ALL {
+            let transform = |severity| corruption.transform(severity);
+            let mild = transform(1).apply_seeded(digit(), 7);
+            let severe = transform(Corruption::MAX_SEVERITY).apply_seeded(digit(), 7);
+            assert_ne!(mild, digit(), "{corruption:?}");
+            assert_eq!(
+                transform(1).apply_seeded(digit(), 7),
+                mild,
+                "{corruption:?}"
+            );
+            assert!(
+                distance(&severe, &digit()) > distance(&mild, &digit()),
+                "{corruption:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn erasing_and_contrast() {
+        let erased = Erasing { size: 28, value: 9 }.apply_seeded(digit(), 7);
+        assert_eq!(erased, GrayImage28::new([9; 784]));
+        let flat = Contrast { factor: 0.0 }.apply_seeded(digit(), 7);
+        assert!(
+            flat.as_bytes()
+                .iter()
+                .all(|&byte| byte == flat.as_bytes()[0])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn severity_starts_at_1() {
+        Corruption::Blur.transform(0);
+    }
+
+    #[test]
+    fn corrupted_test_data_keeps_labels() {
+        let copy = corrupted::<TestData>(Corruption::Scratches, 2, 7);
+        assert_eq!(copy.len(), TestImage::COUNT);
+        let (image, label) = copy[1234];
+        assert_eq!(label, TestLabel::from_index(1234).unwrap());
+        assert_ne!(image, TestImage::from_index(1234).unwrap().into());
+    }
+}
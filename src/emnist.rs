@@ -0,0 +1,344 @@
+//! [EMNIST](https://www.nist.gov/itl/products-and-services/emnist-dataset): handwritten digits and letters
+//! from NIST Special Database 19, converted to the MNIST layout.
+//!
+//! EMNIST has one pair of training and test files per [EmnistVariant].
+//! Its images are stored transposed, so they are transposed back when they are loaded.
+
+use crate::{
+    ClassSet, DigitClass, Files, SourceData, SourceImage, SourceLabel, Test, Training,
+    files::{FileBytes, FileKind, Slots, Source},
+    image::transpose_images,
+};
+
+use core::{cmp::Ordering, convert::Infallible, fmt, hash, marker::PhantomData};
+use std::borrow::Cow;
+
+/// The EMNIST dataset with the classes of the variant `V`.
+/// Read from `dataset/emnist/` or `$EMNIST_DATASET_DIR` by default
+pub struct Emnist<V>(Infallible, PhantomData<fn() -> V>);
+impl<V: EmnistVariant> Source for Emnist<V> {
+    type Class = V::Class;
+    const NAME: &'static str = V::NAME;
+    const DIRECTORY_VARIABLE: &'static str = "EMNIST_DATASET_DIR";
+    const DIRECTORY: &'static str = "dataset/emnist";
+
+    /// The names used by the original distribution
+    fn file_names(kind: FileKind) -> &'static [&'static str] {
+        let index = kind as usize;
+        &V::FILE_NAMES[index..=index]
+    }
+
+    fn slots() -> &'static Slots<Self> {
+        V::slots()
+    }
+
+    /// The images are stored column by column
+    fn decode(kind: FileKind, mut bytes: FileBytes) -> FileBytes {
+        if let FileKind::TrainingImages | FileKind::TestImages = kind {
+            transpose_images(bytes.to_mut());
+        }
+        bytes
+    }
+
+    fn decode_copies(kind: FileKind) -> bool {
+        matches!(kind, FileKind::TrainingImages | FileKind::TestImages)
+    }
+
+    fn encode(kind: FileKind, bytes: &[u8]) -> Cow<'_, [u8]> {
+        match kind {
+            FileKind::TrainingImages | FileKind::TestImages => {
+                let mut bytes = bytes.to_vec();
+                transpose_images(&mut bytes);
+                Cow::Owned(bytes)
+            }
+            FileKind::TrainingLabels | FileKind::TestLabels => Cow::Borrowed(bytes),
+        }
+    }
+}
+
+/// One of the ways EMNIST groups its characters into classes
+pub trait EmnistVariant: Sized + 'static {
+    type Class: ClassSet;
+    /// see [Source::NAME]
+    const NAME: &'static str;
+    /// Indexed by [FileKind]
+    const FILE_NAMES: &'static [&'static str; 4];
+    /// see [Source::slots]
+    fn slots() -> &'static Slots<Emnist<Self>>;
+}
+
+/// The characters of an [EmnistVariant] whose classes are [EmnistClass]es
+pub trait CharacterSet: 'static {
+    /// One ASCII character per class, in class order
+    const CHARACTERS: &'static str;
+    /// The label byte of the first class
+    const FIRST_LABEL: u8 = 0;
+}
+
+/// 47 classes: digits, upper case letters and the lower case letters that don't look like their upper case.
+/// 112,800 training and 18,800 test images with the same number of images per class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Balanced {}
+/// 62 classes: digits, upper case and lower case letters. 697,932 training and 116,323 test images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByClass {}
+/// The 47 classes of [Balanced] with all of the images of [ByClass]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByMerge {}
+/// 26 classes of letters where upper and lower case are merged. 124,800 training and 20,800 test images.
+/// The labels are `1..=26`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Letters {}
+/// 10 classes of digits. 240,000 training and 40,000 test images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Digits {}
+/// 10 classes of digits with the same number of images as MNIST
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnist {}
+
+/// The merged classes of [Balanced] and [ByMerge]: `c i j k l m o p s u v w x y z` only have an upper case class
+const MERGED_CHARACTERS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabdefghnqrt";
+
+impl EmnistVariant for Balanced {
+    type Class = EmnistClass<Self>;
+    const NAME: &'static str = "EMNIST balanced";
+    const FILE_NAMES: &'static [&'static str; 4] = &[
+        "emnist-balanced-train-images-idx3-ubyte",
+        "emnist-balanced-train-labels-idx1-ubyte",
+        "emnist-balanced-test-images-idx3-ubyte",
+        "emnist-balanced-test-labels-idx1-ubyte",
+    ];
+    fn slots() -> &'static Slots<Emnist<Self>> {
+        static SLOTS: Slots<Emnist<Balanced>> = Slots::new();
+        &SLOTS
+    }
+}
+impl CharacterSet for Balanced {
+    const CHARACTERS: &'static str = MERGED_CHARACTERS;
+}
+
+impl EmnistVariant for ByClass {
+    type Class = EmnistClass<Self>;
+    const NAME: &'static str = "EMNIST byclass";
+    const FILE_NAMES: &'static [&'static str; 4] = &[
+        "emnist-byclass-train-images-idx3-ubyte",
+        "emnist-byclass-train-labels-idx1-ubyte",
+        "emnist-byclass-test-images-idx3-ubyte",
+        "emnist-byclass-test-labels-idx1-ubyte",
+    ];
+    fn slots() -> &'static Slots<Emnist<Self>> {
+        static SLOTS: Slots<Emnist<ByClass>> = Slots::new();
+        &SLOTS
+    }
+}
+impl CharacterSet for ByClass {
+    const CHARACTERS: &'static str =
+        "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+}
+
+impl EmnistVariant for ByMerge {
+    type Class = EmnistClass<Self>;
+    const NAME: &'static str = "EMNIST bymerge";
+    const FILE_NAMES: &'static [&'static str; 4] = &[
+        "emnist-bymerge-train-images-idx3-ubyte",
+        "emnist-bymerge-train-labels-idx1-ubyte",
+        "emnist-bymerge-test-images-idx3-ubyte",
+        "emnist-bymerge-test-labels-idx1-ubyte",
+    ];
+    fn slots() -> &'static Slots<Emnist<Self>> {
+        static SLOTS: Slots<Emnist<ByMerge>> = Slots::new();
+        &SLOTS
+    }
+}
+impl CharacterSet for ByMerge {
+    const CHARACTERS: &'static str = MERGED_CHARACTERS;
+}
+
+impl EmnistVariant for Letters {
+    type Class = EmnistClass<Self>;
+    const NAME: &'static str = "EMNIST letters";
+    const FILE_NAMES: &'static [&'static str; 4] = &[
+        "emnist-letters-train-images-idx3-ubyte",
+        "emnist-letters-train-labels-idx1-ubyte",
+        "emnist-letters-test-images-idx3-ubyte",
+        "emnist-letters-test-labels-idx1-ubyte",
+    ];
+    fn slots() -> &'static Slots<Emnist<Self>> {
+        static SLOTS: Slots<Emnist<Letters>> = Slots::new();
+        &SLOTS
+    }
+}
+impl CharacterSet for Letters {
+    const CHARACTERS: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const FIRST_LABEL: u8 = 1;
+}
+
+impl EmnistVariant for Digits {
+    type Class = DigitClass;
+    const NAME: &'static str = "EMNIST digits";
+    const FILE_NAMES: &'static [&'static str; 4] = &[
+        "emnist-digits-train-images-idx3-ubyte",
+        "emnist-digits-train-labels-idx1-ubyte",
+        "emnist-digits-test-images-idx3-ubyte",
+        "emnist-digits-test-labels-idx1-ubyte",
+    ];
+    fn slots() -> &'static Slots<Emnist<Self>> {
+        static SLOTS: Slots<Emnist<Digits>> = Slots::new();
+        &SLOTS
+    }
+}
+
+impl EmnistVariant for Mnist {
+    type Class = DigitClass;
+    const NAME: &'static str = "EMNIST mnist";
+    const FILE_NAMES: &'static [&'static str; 4] = &[
+        "emnist-mnist-train-images-idx3-ubyte",
+        "emnist-mnist-train-labels-idx1-ubyte",
+        "emnist-mnist-test-images-idx3-ubyte",
+        "emnist-mnist-test-labels-idx1-ubyte",
+    ];
+    fn slots() -> &'static Slots<Emnist<Self>> {
+        static SLOTS: Slots<Emnist<Mnist>> = Slots::new();
+        &SLOTS
+    }
+}
+
+/// A class of the [CharacterSet] `V`
+pub struct EmnistClass<V>(u8, PhantomData<fn() -> V>);
+impl<V: CharacterSet> EmnistClass<V> {
+    /// The character this class stands for. The upper case letter if the cases are merged
+    pub fn character(self) -> char {
+        V::CHARACTERS.as_bytes()[self.0 as usize] as char
+    }
+}
+impl<V: CharacterSet> ClassSet for EmnistClass<V> {
+    const COUNT: usize = V::CHARACTERS.len();
+    fn from_index(index: usize) -> Option<Self> {
+        if index < Self::COUNT {
+            Some(Self(index as u8, PhantomData))
+        } else {
+            None
+        }
+    }
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+    fn name(self) -> &'static str {
+        let index = self.index();
+        &V::CHARACTERS[index..=index]
+    }
+    fn from_byte(b: u8) -> Option<Self> {
+        Self::from_index(b.checked_sub(V::FIRST_LABEL)? as usize)
+    }
+    fn to_byte(self) -> u8 {
+        self.0 + V::FIRST_LABEL
+    }
+}
+impl<V: CharacterSet> fmt::Debug for EmnistClass<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EmnistClass")
+            .field(&self.character())
+            .finish()
+    }
+}
+impl<V> Clone for EmnistClass<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<V> Copy for EmnistClass<V> {}
+impl<V> PartialEq for EmnistClass<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<V> Eq for EmnistClass<V> {}
+impl<V> PartialOrd for EmnistClass<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<V> Ord for EmnistClass<V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+impl<V> hash::Hash for EmnistClass<V> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+/// The contents of the four files of an EMNIST variant, read at runtime
+pub type EmnistFiles<V> = Files<Emnist<V>>;
+
+/// A handle to a specific training [Image](crate::Image) from an EMNIST variant
+pub type EmnistTrainingImage<V> = SourceImage<Emnist<V>, Training>;
+/// A handle to a specific training [Label](crate::Label) from an EMNIST variant
+pub type EmnistTrainingLabel<V> = SourceLabel<Emnist<V>, Training>;
+/// A handle to a specific test [Image](crate::Image) from an EMNIST variant
+pub type EmnistTestImage<V> = SourceImage<Emnist<V>, Test>;
+/// A handle to a specific test [Label](crate::Label) from an EMNIST variant
+pub type EmnistTestLabel<V> = SourceLabel<Emnist<V>, Test>;
+
+pub type EmnistTrainingData<V> = SourceData<Emnist<V>, Training>;
+pub type EmnistTestData<V> = SourceData<Emnist<V>, Test>;
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassSet, DigitClass, IMAGE_OFFSET, IMAGE_SIZE, IMAGE_WIDTH, LABEL_OFFSET,
+        emnist::{Balanced, ByClass, Digits, EmnistClass, EmnistFiles, Letters},
+        idx,
+        test_util::temp_directory,
+    };
+
+    #[test]
+    fn class_mappings() {
+        assert_eq!(<EmnistClass<Balanced> as ClassSet>::COUNT, 47);
+        assert_eq!(<EmnistClass<ByClass> as ClassSet>::COUNT, 62);
+        assert_eq!(<EmnistClass<Letters> as ClassSet>::COUNT, 26);
+
+        let t = EmnistClass::<Balanced>::from_index(46).unwrap();
+        assert_eq!((t.character(), t.name()), ('t', "t"));
+        assert_eq!(EmnistClass::<ByClass>::from_index(61).unwrap().name(), "z");
+        assert_eq!(EmnistClass::<ByClass>::from_index(62), None);
+
+        assert_eq!(EmnistClass::<Letters>::from_byte(0), None);
+        let a = EmnistClass::<Letters>::from_byte(1).unwrap();
+        assert_eq!((a.index(), a.character(), a.to_byte()), (0, 'A', 1));
+        assert_eq!(EmnistClass::<Letters>::from_byte(26).unwrap().name(), "Z");
+        assert_eq!(EmnistClass::<Letters>::from_byte(27), None);
+    }
+
+    #[test]
+    fn images_are_transposed() {
+        // the pixel at x = 0, y = 1 as it is stored on disk
+        let mut stored = [0; IMAGE_SIZE];
+        stored[1] = u8::MAX;
+        let mut images = Vec::new();
+        idx::write_images(&mut images, [stored]).unwrap();
+        let mut labels = Vec::new();
+        idx::write_labels(&mut labels, [DigitClass::One]).unwrap();
+
+        let files =
+            EmnistFiles::<Digits>::from_bytes(images.clone(), labels.clone(), images, labels)
+                .unwrap();
+        assert_eq!(files.training_images()[IMAGE_OFFSET + 1], 0);
+        assert_eq!(files.training_images()[IMAGE_OFFSET + IMAGE_WIDTH], u8::MAX);
+        assert_eq!(files.training_labels()[LABEL_OFFSET], 1);
+
+        let directory = temp_directory("emnist_images_are_transposed");
+        files.write(&directory).unwrap();
+        assert_eq!(EmnistFiles::<Digits>::open(&directory).unwrap(), files);
+        #[cfg(feature = "mmap")]
+        assert!(matches!(
+            unsafe { EmnistFiles::<Digits>::map(&directory) },
+            Err(crate::MnistError::CannotMap {
+                kind: crate::files::FileKind::TrainingImages,
+                ..
+            })
+        ));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
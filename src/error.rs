@@ -0,0 +1,113 @@
+use crate::files::FileKind;
+
+use core::fmt;
+use std::io;
+
+/// Everything that can be wrong with a dataset file
+#[derive(Debug)]
+pub enum MnistError {
+    /// The file couldn't be read
+    Io(io::Error),
+    /// The file doesn't start with a valid IDX magic number
+    UnknownMagicNumber(u32),
+    /// The file is a valid IDX file but not the kind that was expected, e.g. a labels file where an images file was expected
+    BadMagicNumber { expected: u32, actual: u32 },
+    /// The dimensions in the header are not the ones that were expected, e.g. images that aren't `28x28`
+    WrongDimensions {
+        expected: Vec<usize>,
+        actual: Vec<usize>,
+    },
+    /// The file is shorter than its header says it should be
+    Truncated { expected: usize, actual: usize },
+    /// The file is longer than its header says it should be
+    TrailingData { expected: usize, actual: usize },
+    /// An images file and its labels file don't contain the same number of items
+    CountMismatch { images: usize, labels: usize },
+    /// The label at `index` isn't a known class. `value` is the byte of a labels file, or the `i32` of a QMNIST labels file
+    LabelOutOfRange { index: usize, value: i32 },
+    /// The file doesn't have the known SHA-256 digest of the `kind` file of `dataset`, e.g. because it was modified
+    ChecksumMismatch {
+        dataset: &'static str,
+        kind: FileKind,
+    },
+    /// The `kind` file of `dataset` has to be decoded into memory, so it can't be memory mapped
+    CannotMap {
+        dataset: &'static str,
+        kind: FileKind,
+    },
+    /// No SHA-256 digest is known for the `kind` file of `dataset`
+    UnknownFile {
+        dataset: &'static str,
+        kind: FileKind,
+    },
+    /// The SHA-256 digest of the file is the known digest of a different file
+    WrongFile {
+        expected_dataset: &'static str,
+        expected_kind: FileKind,
+        actual_dataset: &'static str,
+        actual_kind: FileKind,
+    },
+}
+impl fmt::Display for MnistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::UnknownMagicNumber(actual) => {
+                write!(f, "{actual:#010x} is not an IDX magic number")
+            }
+            Self::BadMagicNumber { expected, actual } => {
+                write!(f, "expected magic number {expected}, found {actual}")
+            }
+            Self::WrongDimensions { expected, actual } => {
+                write!(f, "expected dimensions {expected:?}, found {actual:?}")
+            }
+            Self::Truncated { expected, actual } => {
+                write!(f, "expected {expected} bytes, found only {actual}")
+            }
+            Self::TrailingData { expected, actual } => {
+                write!(f, "expected {expected} bytes, found {actual}")
+            }
+            Self::CountMismatch { images, labels } => {
+                write!(f, "found {images} images but {labels} labels")
+            }
+            Self::LabelOutOfRange { index, value } => {
+                write!(f, "label {index} has the unknown value {value}")
+            }
+            Self::ChecksumMismatch { dataset, kind } => {
+                write!(
+                    f,
+                    "the SHA-256 digest is not the one of the {dataset} {kind:?}"
+                )
+            }
+            Self::CannotMap { dataset, kind } => write!(
+                f,
+                "the {dataset} {kind:?} has to be decoded into memory, so it can't be memory mapped"
+            ),
+            Self::UnknownFile { dataset, kind } => {
+                write!(f, "no SHA-256 digest is known for the {dataset} {kind:?}")
+            }
+            Self::WrongFile {
+                expected_dataset,
+                expected_kind,
+                actual_dataset,
+                actual_kind,
+            } => write!(
+                f,
+                "expected the {expected_dataset} {expected_kind:?}, found the {actual_dataset} {actual_kind:?}"
+            ),
+        }
+    }
+}
+impl std::error::Error for MnistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for MnistError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
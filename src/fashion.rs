@@ -0,0 +1,145 @@
+//! [Fashion-MNIST](https://github.com/zalandoresearch/fashion-mnist): 28x28 gray-scale images of clothing
+//! stored in the same IDX layout as MNIST
+
+use crate::{
+    ClassSet, Files, SourceData, SourceImage, SourceLabel, Test, Training,
+    files::{FileKind, Slots, Source},
+};
+
+/// The Fashion-MNIST dataset.
+/// Read from `dataset/fashion/` or `$FASHION_MNIST_DATASET_DIR` by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FashionMnist {}
+impl Source for FashionMnist {
+    type Class = FashionClass;
+    const NAME: &'static str = "Fashion-MNIST";
+    const DIRECTORY_VARIABLE: &'static str = "FASHION_MNIST_DATASET_DIR";
+    const DIRECTORY: &'static str = "dataset/fashion";
+
+    /// The first name is the one used by the original distribution
+    fn file_names(kind: FileKind) -> &'static [&'static str] {
+        match kind {
+            FileKind::TrainingImages => &["train-images-idx3-ubyte", "train-images.idx3-ubyte"],
+            FileKind::TrainingLabels => &["train-labels-idx1-ubyte", "train-labels.idx1-ubyte"],
+            FileKind::TestImages => &["t10k-images-idx3-ubyte", "t10k-images.idx3-ubyte"],
+            FileKind::TestLabels => &["t10k-labels-idx1-ubyte", "t10k-labels.idx1-ubyte"],
+        }
+    }
+
+    fn slots() -> &'static Slots<Self> {
+        static SLOTS: Slots<FashionMnist> = Slots::new();
+        &SLOTS
+    }
+}
+
+/// The possible classes of clothing in the Fashion-MNIST dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FashionClass {
+    TShirt = 0,
+    Trouser = 1,
+    Pullover = 2,
+    Dress = 3,
+    Coat = 4,
+    Sandal = 5,
+    Shirt = 6,
+    Sneaker = 7,
+    Bag = 8,
+    AnkleBoot = 9,
+}
+impl FashionClass {
+    /// The number of classes of clothing
+    pub const COUNT: usize = 10;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::TShirt,
+        Self::Trouser,
+        Self::Pullover,
+        Self::Dress,
+        Self::Coat,
+        Self::Sandal,
+        Self::Shirt,
+        Self::Sneaker,
+        Self::Bag,
+        Self::AnkleBoot,
+    ];
+    pub const fn from_byte(b: u8) -> Option<Self> {
+        if (b as usize) < Self::COUNT {
+            Some(Self::ALL[b as usize])
+        } else {
+            None
+        }
+    }
+    /// The names used by the original distribution
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::TShirt => "T-shirt/top",
+            Self::Trouser => "Trouser",
+            Self::Pullover => "Pullover",
+            Self::Dress => "Dress",
+            Self::Coat => "Coat",
+            Self::Sandal => "Sandal",
+            Self::Shirt => "Shirt",
+            Self::Sneaker => "Sneaker",
+            Self::Bag => "Bag",
+            Self::AnkleBoot => "Ankle boot",
+        }
+    }
+    /// see https://en.wikipedia.org/wiki/One-hot
+    pub const fn one_hot_encode(self) -> [f32; Self::COUNT] {
+        let mut encoded = [0.0; Self::COUNT];
+        encoded[self as usize] = 1.0;
+        encoded
+    }
+}
+impl From<FashionClass> for usize {
+    fn from(value: FashionClass) -> Self {
+        value as usize
+    }
+}
+
+impl ClassSet for FashionClass {
+    const COUNT: usize = Self::COUNT;
+    fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+    fn index(self) -> usize {
+        self as usize
+    }
+    fn name(self) -> &'static str {
+        FashionClass::name(self)
+    }
+}
+
+/// The contents of the four Fashion-MNIST files, read at runtime
+pub type FashionFiles = Files<FashionMnist>;
+
+/// A handle to a specific training [Image](crate::Image) from the Fashion-MNIST dataset
+pub type FashionTrainingImage = SourceImage<FashionMnist, Training>;
+/// A handle to a specific training [Label](crate::Label) from the Fashion-MNIST dataset
+pub type FashionTrainingLabel = SourceLabel<FashionMnist, Training>;
+/// A handle to a specific test [Image](crate::Image) from the Fashion-MNIST dataset
+pub type FashionTestImage = SourceImage<FashionMnist, Test>;
+/// A handle to a specific test [Label](crate::Label) from the Fashion-MNIST dataset
+pub type FashionTestLabel = SourceLabel<FashionMnist, Test>;
+
+pub type FashionTrainingData = SourceData<FashionMnist, Training>;
+pub type FashionTestData = SourceData<FashionMnist, Test>;
+
+#[cfg(test)]
+mod test {
+    use crate::{ClassSet, fashion::FashionClass};
+
+    #[test]
+    fn class_bytes_round_trip() {
+        for class in FashionClass::ALL {
+            assert_eq!(FashionClass::from_byte(class as u8), Some(class));
+            assert_eq!(class.one_hot_encode()[class as usize], 1.0);
+            assert_eq!(class.one_hot_encode().iter().sum::<f32>(), 1.0);
+        }
+        assert_eq!(FashionClass::from_byte(10), None);
+        assert_eq!(
+            <FashionClass as ClassSet>::all().collect::<Vec<_>>(),
+            FashionClass::ALL
+        );
+        assert_eq!(FashionClass::AnkleBoot.name(), "Ankle boot");
+    }
+}
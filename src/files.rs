@@ -0,0 +1,798 @@
+use crate::{ClassSet, DigitClass, IMAGE_SIZE, MnistError, idx, validate_images, validate_labels};
+
+use core::{borrow::Borrow, fmt, marker::PhantomData, ops::Deref};
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// One of the four files that make up a [Source]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    TrainingImages = 0,
+    TrainingLabels = 1,
+    TestImages = 2,
+    TestLabels = 3,
+}
+impl FileKind {
+    pub const ALL: [Self; 4] = [
+        Self::TrainingImages,
+        Self::TrainingLabels,
+        Self::TestImages,
+        Self::TestLabels,
+    ];
+}
+
+/// Which half of a [Source] a handle indexes into
+pub trait Split: 'static {
+    const IMAGES: FileKind;
+    const LABELS: FileKind;
+}
+/// The training half of a [Source]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Training {}
+impl Split for Training {
+    const IMAGES: FileKind = FileKind::TrainingImages;
+    const LABELS: FileKind = FileKind::TrainingLabels;
+}
+/// The test half of a [Source]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Test {}
+impl Split for Test {
+    const IMAGES: FileKind = FileKind::TestImages;
+    const LABELS: FileKind = FileKind::TestLabels;
+}
+
+/// A dataset distributed as four IDX files laid out like MNIST.
+///
+/// Implemented by marker types like [Mnist] so that the [Image](crate::Image) and [Label](crate::Label)
+/// handles, [DataSet](crate::DataSet)s and [Files] can be switched between datasets with a type parameter
+pub trait Source: Sized + 'static {
+    /// The classes the labels of this dataset specify
+    type Class: ClassSet;
+    /// A human readable name, e.g. to report which dataset a file belongs to
+    const NAME: &'static str;
+    /// Setting this environment variable overrides the default directory
+    const DIRECTORY_VARIABLE: &'static str;
+    /// The directory, relative to this crate, the files are read from by default
+    const DIRECTORY: &'static str;
+
+    /// Names the file can have. The first name is used by [Files::write]
+    fn file_names(kind: FileKind) -> &'static [&'static str];
+
+    /// The storage behind [Files::install] and the lazily loaded default files.
+    /// Every implementation needs its own `static`
+    fn slots() -> &'static Slots<Self>;
+
+    /// Files compiled into the binary. These take precedence over everything else.
+    /// They must already be [decoded](Source::decode) and are checked with [Source::validate] the first time
+    /// a file of their [Split] is used
+    fn embedded(kind: FileKind) -> Option<&'static [u8]> {
+        let _ = kind;
+        None
+    }
+
+    /// Checks a file as it is stored on disk. Returns the number of items in it
+    fn validate(kind: FileKind, bytes: &[u8]) -> Result<usize, MnistError> {
+        match kind {
+            FileKind::TrainingImages | FileKind::TestImages => validate_images(bytes),
+            FileKind::TrainingLabels | FileKind::TestLabels => {
+                validate_labels::<Self::Class>(bytes)
+            }
+        }
+    }
+
+    /// Converts a validated file from the layout it has on disk into the layout the handles expect:
+    /// an images file of upright `28x28` images or a labels file that starts like an `idx1` file.
+    /// Embedded files must already be decoded
+    fn decode(kind: FileKind, bytes: FileBytes) -> FileBytes {
+        let _ = kind;
+        bytes
+    }
+
+    /// Whether [Source::decode] copies the file into memory instead of passing its bytes through.
+    /// Such files are never memory mapped
+    fn decode_copies(kind: FileKind) -> bool {
+        let _ = kind;
+        false
+    }
+
+    /// The inverse of [Source::decode], used by [Files::write]
+    fn encode(kind: FileKind, bytes: &[u8]) -> Cow<'_, [u8]> {
+        let _ = kind;
+        Cow::Borrowed(bytes)
+    }
+
+    /// `$DIRECTORY_VARIABLE` if it is set, otherwise [Source::DIRECTORY] in this crate
+    fn default_directory() -> PathBuf {
+        std::env::var_os(Self::DIRECTORY_VARIABLE)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join(Self::DIRECTORY))
+    }
+
+    /// The file that [Image](crate::Image) and [Label](crate::Label) handles of this source index into.
+    ///
+    /// Both files of a [Split] are loaded and validated together the first time either of them is used,
+    /// so an images file and a labels file with different counts are never used together.
+    /// If only one of them exists it is validated on its own, e.g. to use the labels without their images.
+    /// The default files are always read into memory, use [Files::map] and [Files::install] to memory map them.
+    ///
+    /// # Panics
+    /// if nothing is installed and the file can't be loaded from [Source::default_directory],
+    /// or if the [embedded](Source::embedded) files are malformed
+    fn file(kind: FileKind) -> &'static [u8] {
+        let slots = Self::slots();
+        let split = kind as usize / 2;
+        let [images, labels] = [FileKind::ALL[2 * split], FileKind::ALL[2 * split + 1]];
+        if let Some(bytes) = Self::embedded(kind) {
+            slots.embedded_validated[split].get_or_init(|| {
+                let file = |kind| Self::embedded(kind).expect("sources embed all four files");
+                validate_pair::<Self>([images, labels], file(images), file(labels))
+                    .unwrap_or_else(|error| panic!("the embedded {kind:?} is malformed: {error}"))
+            });
+            return bytes;
+        }
+        match slots
+            .files
+            .get_or_init(|| Loaded::Defaults([const { OnceLock::new() }; 2]))
+        {
+            Loaded::Installed(files) => files.file(kind),
+            Loaded::Defaults(defaults) => {
+                let directory = Self::default_directory();
+                let pair = defaults[split].get_or_init(|| {
+                    load_pair::<Self>(&directory, [images, labels]).unwrap_or_else(|error| {
+                        panic!(
+                            "failed to load {images:?} and {labels:?} from {}: {error}",
+                            directory.display()
+                        )
+                    })
+                });
+                match &pair[kind as usize % 2] {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        panic!(
+                            "failed to load {kind:?} from {}: {error}",
+                            directory.display()
+                        )
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// see [Source::slots]
+pub struct Slots<S> {
+    /// Decided once by [Files::install] or by the first use of a handle, whichever comes first
+    files: OnceLock<Loaded<S>>,
+    /// Whether the embedded files of each [Split] were validated
+    embedded_validated: [OnceLock<()>; 2],
+}
+impl<S> Slots<S> {
+    pub const fn new() -> Self {
+        Self {
+            files: OnceLock::new(),
+            embedded_validated: [const { OnceLock::new() }; 2],
+        }
+    }
+}
+
+/// The files behind the handles of a [Source]
+enum Loaded<S> {
+    Installed(Files<S>),
+    /// The `[images, labels]` of each [Split] loaded from the default directory on first use, see [load_pair]
+    Defaults([OnceLock<[io::Result<FileBytes>; 2]>; 2]),
+}
+impl<S> Default for Slots<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The contents of one file, either read into memory or memory mapped
+pub enum FileBytes {
+    Owned(Box<[u8]>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+impl FileBytes {
+    /// Reads mapped bytes into memory first, like [Cow::to_mut]
+    pub fn to_mut(&mut self) -> &mut [u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mapped) => {
+                *self = Self::Owned(mapped[..].into());
+                self.to_mut()
+            }
+        }
+    }
+    pub fn is_mapped(&self) -> bool {
+        match self {
+            Self::Owned(_) => false,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_) => true,
+        }
+    }
+}
+impl Deref for FileBytes {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mapped) => mapped,
+        }
+    }
+}
+impl From<Box<[u8]>> for FileBytes {
+    fn from(value: Box<[u8]>) -> Self {
+        Self::Owned(value)
+    }
+}
+/// Mapped bytes are cloned into memory
+impl Clone for FileBytes {
+    fn clone(&self) -> Self {
+        Self::Owned(self[..].into())
+    }
+}
+impl PartialEq for FileBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self[..] == other[..]
+    }
+}
+impl Eq for FileBytes {}
+impl fmt::Debug for FileBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileBytes")
+            .field("len", &self.len())
+            .field("mapped", &self.is_mapped())
+            .finish()
+    }
+}
+
+/// The original MNIST dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnist {}
+impl Source for Mnist {
+    type Class = DigitClass;
+    const NAME: &'static str = "MNIST";
+    const DIRECTORY_VARIABLE: &'static str = "MNIST_DATASET_DIR";
+    const DIRECTORY: &'static str = "dataset";
+
+    /// The first name is the one used in `dataset/`, the second is the one used by the original distribution
+    fn file_names(kind: FileKind) -> &'static [&'static str] {
+        match kind {
+            FileKind::TrainingImages => &["train-images.idx3-ubyte", "train-images-idx3-ubyte"],
+            FileKind::TrainingLabels => &["train-labels.idx1-ubyte", "train-labels-idx1-ubyte"],
+            FileKind::TestImages => &["t10k-images.idx3-ubyte", "t10k-images-idx3-ubyte"],
+            FileKind::TestLabels => &["t10k-labels.idx1-ubyte", "t10k-labels-idx1-ubyte"],
+        }
+    }
+
+    fn slots() -> &'static Slots<Self> {
+        static SLOTS: Slots<Mnist> = Slots::new();
+        &SLOTS
+    }
+
+    #[cfg(feature = "embed")]
+    fn embedded(kind: FileKind) -> Option<&'static [u8]> {
+        Some(match kind {
+            FileKind::TrainingImages => include_bytes!("../dataset/train-images.idx3-ubyte"),
+            FileKind::TrainingLabels => include_bytes!("../dataset/train-labels.idx1-ubyte"),
+            FileKind::TestImages => include_bytes!("../dataset/t10k-images.idx3-ubyte"),
+            FileKind::TestLabels => include_bytes!("../dataset/t10k-labels.idx1-ubyte"),
+        })
+    }
+}
+
+/// The contents of the four MNIST files, read at runtime
+pub type MnistFiles = Files<Mnist>;
+
+/// The contents of the four files of the [Source] `S`, read at runtime.
+///
+/// Use [Files::install] to make these the data behind the [Image](crate::Image) and [Label](crate::Label) handles of `S`.
+/// If nothing is installed each file is read from [Source::default_directory] the first time one of its handles is used.
+pub struct Files<S> {
+    files: [FileBytes; 4],
+    source: PhantomData<fn() -> S>,
+}
+impl<S: Source> Files<S> {
+    /// Reads all four files from `directory` and validates them with [Files::from_bytes]
+    pub fn open(directory: impl AsRef<Path>) -> Result<Self, MnistError> {
+        let directory = directory.as_ref();
+        let [training_images, training_labels, test_images, test_labels] =
+            FileKind::ALL.map(|kind| read_first_existing(directory, S::file_names(kind)));
+        Self::from_file_bytes([
+            training_images?,
+            training_labels?,
+            test_images?,
+            test_labels?,
+        ])
+    }
+
+    /// Like [Files::open] but memory maps the files instead of reading them, so that
+    /// [Image::as_bytes](crate::Image::as_bytes) borrows straight from the mapping.
+    /// gzip compressed files are still decompressed into memory.
+    ///
+    /// Fails with [MnistError::CannotMap] if `S` has to [decode](Source::decode_copies) a file into memory,
+    /// e.g. the transposed images of EMNIST; use [Files::open] for those
+    ///
+    /// # Safety
+    /// The files must not be modified or truncated while they are mapped, see [memmap2::Mmap::map]
+    #[cfg(feature = "mmap")]
+    pub unsafe fn map(directory: impl AsRef<Path>) -> Result<Self, MnistError> {
+        if let Some(kind) = FileKind::ALL
+            .into_iter()
+            .find(|&kind| S::decode_copies(kind))
+        {
+            return Err(MnistError::CannotMap {
+                dataset: S::NAME,
+                kind,
+            });
+        }
+        let directory = directory.as_ref();
+        let [training_images, training_labels, test_images, test_labels] = FileKind::ALL
+            // SAFETY: upheld by the caller
+            .map(|kind| unsafe { map_first_existing(directory, S::file_names(kind)) });
+        Self::from_file_bytes([
+            training_images?,
+            training_labels?,
+            test_images?,
+            test_labels?,
+        ])
+    }
+
+    /// Fails if any of the files is malformed or if an images file and its labels file have different counts.
+    /// The files are expected in the layout they have on disk, see [Source::decode].
+    /// gzip compressed files are decompressed if the `gzip` feature is enabled
+    pub fn from_bytes(
+        training_images: impl Into<Box<[u8]>>,
+        training_labels: impl Into<Box<[u8]>>,
+        test_images: impl Into<Box<[u8]>>,
+        test_labels: impl Into<Box<[u8]>>,
+    ) -> Result<Self, MnistError> {
+        Self::from_file_bytes([
+            training_images.into().into(),
+            training_labels.into().into(),
+            test_images.into().into(),
+            test_labels.into().into(),
+        ])
+    }
+
+    fn from_file_bytes(files: [FileBytes; 4]) -> Result<Self, MnistError> {
+        let [training_images, training_labels, test_images, test_labels] = files.map(decompress);
+        let files = [
+            training_images?,
+            training_labels?,
+            test_images?,
+            test_labels?,
+        ];
+        validate_pair::<S>([Training::IMAGES, Training::LABELS], &files[0], &files[1])?;
+        validate_pair::<S>([Test::IMAGES, Test::LABELS], &files[2], &files[3])?;
+        let mut kinds = FileKind::ALL.into_iter();
+        Ok(Self::new(files.map(|bytes| {
+            S::decode(kinds.next().expect("one kind per file"), bytes)
+        })))
+    }
+
+    /// Builds all four files from upright `(image, class)` pairs, e.g. a filtered or augmented copy of
+    /// `TrainingData::all().map(|(image, label)| (image.as_bytes(), label.class()))`
+    pub fn from_examples<I: Borrow<[u8; IMAGE_SIZE]>>(
+        training: impl IntoIterator<Item = (I, S::Class)>,
+        test: impl IntoIterator<Item = (I, S::Class)>,
+    ) -> Self {
+        let (training_images, training_labels) = examples_to_bytes(training);
+        let (test_images, test_labels) = examples_to_bytes(test);
+        Self::new([
+            training_images.into_boxed_slice().into(),
+            training_labels.into_boxed_slice().into(),
+            test_images.into_boxed_slice().into(),
+            test_labels.into_boxed_slice().into(),
+        ])
+    }
+
+    /// Writes all four files to `directory` so that [Files::open] can read them back.
+    /// Uses the first name of each [Source::file_names] and [Source::encode]s the files
+    pub fn write(&self, directory: impl AsRef<Path>) -> io::Result<()> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+        FileKind::ALL.into_iter().try_for_each(|kind| {
+            fs::write(
+                directory.join(S::file_names(kind)[0]),
+                S::encode(kind, self.file(kind)),
+            )
+        })
+    }
+
+    /// `$DIRECTORY_VARIABLE` if it is set, otherwise [Source::DIRECTORY] in this crate
+    pub fn default_directory() -> PathBuf {
+        S::default_directory()
+    }
+
+    /// Makes these files the data behind the [Image](crate::Image) and [Label](crate::Label) handles of `S`.
+    ///
+    /// Returns `Err(self)` if files were already installed, if a handle of `S` was already used with the default files,
+    /// or if `S` has [embedded](Source::embedded) files.
+    pub fn install(self) -> Result<(), Self> {
+        if S::embedded(FileKind::TrainingImages).is_some() {
+            return Err(self);
+        }
+        S::slots()
+            .files
+            .set(Loaded::Installed(self))
+            .map_err(|loaded| match loaded {
+                Loaded::Installed(files) => files,
+                Loaded::Defaults(_) => unreachable!("install only sets installed files"),
+            })
+    }
+}
+impl<S> Files<S> {
+    fn new(files: [FileBytes; 4]) -> Self {
+        Self {
+            files,
+            source: PhantomData,
+        }
+    }
+
+    pub fn file(&self, kind: FileKind) -> &[u8] {
+        &self.files[kind as usize]
+    }
+    pub fn training_images(&self) -> &[u8] {
+        self.file(FileKind::TrainingImages)
+    }
+    pub fn training_labels(&self) -> &[u8] {
+        self.file(FileKind::TrainingLabels)
+    }
+    pub fn test_images(&self) -> &[u8] {
+        self.file(FileKind::TestImages)
+    }
+    pub fn test_labels(&self) -> &[u8] {
+        self.file(FileKind::TestLabels)
+    }
+}
+impl<S> fmt::Debug for Files<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Files")
+            .field("training_images", &self.training_images().len())
+            .field("training_labels", &self.training_labels().len())
+            .field("test_images", &self.test_images().len())
+            .field("test_labels", &self.test_labels().len())
+            .finish()
+    }
+}
+impl<S> Clone for Files<S> {
+    fn clone(&self) -> Self {
+        Self::new(self.files.clone())
+    }
+}
+impl<S> PartialEq for Files<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.files == other.files
+    }
+}
+impl<S> Eq for Files<S> {}
+
+/// Checks both files of a [Split] and that they contain the same number of items
+fn validate_pair<S: Source>(
+    [images_kind, labels_kind]: [FileKind; 2],
+    images: &[u8],
+    labels: &[u8],
+) -> Result<(), MnistError> {
+    let images = S::validate(images_kind, images)?;
+    let labels = S::validate(labels_kind, labels)?;
+    if images == labels {
+        Ok(())
+    } else {
+        Err(MnistError::CountMismatch { images, labels })
+    }
+}
+
+/// Reads, validates and [decodes](Source::decode) both files of a [Split] from `directory`.
+/// A file that doesn't exist is returned as its [io::ErrorKind::NotFound] error and the other one is validated on its own
+fn load_pair<S: Source>(
+    directory: &Path,
+    kinds: [FileKind; 2],
+) -> Result<[io::Result<FileBytes>; 2], MnistError> {
+    let [images, labels] =
+        kinds.map(
+            |kind| match read_first_existing(directory, S::file_names(kind)) {
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Err(error)),
+                result => result.map(Ok),
+            },
+        );
+    let [images, labels] = [images?, labels?];
+    match [&images, &labels] {
+        [Ok(images), Ok(labels)] => validate_pair::<S>(kinds, images, labels)?,
+        [Ok(file), Err(_)] | [Err(_), Ok(file)] => {
+            let kind = kinds[usize::from(images.is_err())];
+            S::validate(kind, file)?;
+        }
+        [Err(_), Err(_)] => {}
+    }
+    Ok([
+        images.map(|bytes| S::decode(kinds[0], bytes)),
+        labels.map(|bytes| S::decode(kinds[1], bytes)),
+    ])
+}
+
+/// Returns the contents of an images file and a labels file
+fn examples_to_bytes<I: Borrow<[u8; IMAGE_SIZE]>, C: ClassSet>(
+    examples: impl IntoIterator<Item = (I, C)>,
+) -> (Vec<u8>, Vec<u8>) {
+    let (images, classes): (Vec<I>, Vec<C>) = examples.into_iter().unzip();
+    let mut images_bytes = Vec::new();
+    let mut labels_bytes = Vec::new();
+    idx::write_images(&mut images_bytes, images).expect("writing to a Vec can't fail");
+    idx::write_labels(&mut labels_bytes, classes).expect("writing to a Vec can't fail");
+    (images_bytes, labels_bytes)
+}
+
+/// Every gzip file starts with these two bytes
+const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+
+/// Decompresses `bytes` if they are gzip compressed
+fn decompress(bytes: FileBytes) -> io::Result<FileBytes> {
+    if !bytes.starts_with(&GZIP_MAGIC_NUMBER) {
+        return Ok(bytes);
+    }
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Read;
+        let mut decompressed = Vec::new();
+        flate2::read::MultiGzDecoder::new(&*bytes).read_to_end(&mut decompressed)?;
+        Ok(decompressed.into_boxed_slice().into())
+    }
+    #[cfg(not(feature = "gzip"))]
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "the file is gzip compressed, enable the `gzip` feature to read it",
+    ))
+}
+
+/// Reads and [decompress]es the first of `file_names`, or of `file_names` with a `.gz` extension, that exists in `directory`
+fn read_first_existing(directory: &Path, file_names: &[&str]) -> io::Result<FileBytes> {
+    open_first_existing(directory, file_names, |path| {
+        fs::read(path).map(|bytes| bytes.into_boxed_slice().into())
+    })
+}
+
+/// Like [read_first_existing] but memory maps the file
+///
+/// # Safety
+/// The file must not be modified or truncated while it is mapped, see [memmap2::Mmap::map]
+#[cfg(feature = "mmap")]
+unsafe fn map_first_existing(directory: &Path, file_names: &[&str]) -> io::Result<FileBytes> {
+    open_first_existing(directory, file_names, |path| {
+        let file = fs::File::open(path)?;
+        // SAFETY: upheld by the caller
+        unsafe { memmap2::Mmap::map(&file) }.map(FileBytes::Mapped)
+    })
+}
+
+/// [read_first_existing] with a different way to `open` a file
+fn open_first_existing(
+    directory: &Path,
+    file_names: &[&str],
+    open: impl Fn(&Path) -> io::Result<FileBytes>,
+) -> io::Result<FileBytes> {
+    let paths = file_names.iter().flat_map(|file_name| {
+        [
+            directory.join(file_name),
+            directory.join(format!("{file_name}.gz")),
+        ]
+    });
+    for path in paths {
+        match open(&path) {
+            Ok(bytes) => return decompress(bytes),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "none of {file_names:?} or their .gz exist in {}",
+            directory.display()
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DataSet, DigitClass, IMAGE_MAGIC_NUMBER, IMAGE_SIZE, Image, LABEL_MAGIC_NUMBER,
+        LABEL_OFFSET, Label, MnistError, SourceData, SourceImage, SourceLabel, TrainingLabel,
+        files::{FileKind, Mnist, MnistFiles, Slots, Source, Test, Training, read_first_existing},
+        idx,
+        test_util::{in_memory_source, temp_directory},
+        validate_labels,
+    };
+
+    #[test]
+    fn reads_either_file_name() {
+        let directory = MnistFiles::default_directory();
+        let file_names = Mnist::file_names(FileKind::TestLabels);
+        let dotted = read_first_existing(&directory, file_names).unwrap();
+        let reversed = [file_names[1], file_names[0]];
+        assert_eq!(read_first_existing(&directory, &reversed).unwrap(), dotted);
+    }
+
+    #[test]
+    fn missing_directory_is_not_found() {
+        let error = MnistFiles::open("./does_not_exist").unwrap_err();
+        assert!(
+            matches!(error, MnistError::Io(error) if error.kind() == std::io::ErrorKind::NotFound)
+        );
+    }
+
+    fn example_files() -> MnistFiles {
+        MnistFiles::from_examples(
+            [
+                ([7; IMAGE_SIZE], DigitClass::Seven),
+                ([3; IMAGE_SIZE], DigitClass::Three),
+            ],
+            [([0; IMAGE_SIZE], DigitClass::Zero)],
+        )
+    }
+
+    #[test]
+    fn rejects_corrupted_files() {
+        let files = example_files();
+        let from_bytes = |training_images: &[u8], training_labels: &[u8]| {
+            MnistFiles::from_bytes(
+                training_images,
+                training_labels,
+                files.test_images(),
+                files.test_labels(),
+            )
+        };
+
+        let truncated = &files.training_images()[..files.training_images().len() - 1];
+        assert!(matches!(
+            from_bytes(truncated, files.training_labels()),
+            Err(MnistError::Truncated { .. })
+        ));
+
+        assert!(matches!(
+            from_bytes(files.training_labels(), files.training_labels()),
+            Err(MnistError::BadMagicNumber {
+                expected: IMAGE_MAGIC_NUMBER,
+                actual: LABEL_MAGIC_NUMBER
+            })
+        ));
+
+        let mut out_of_range = files.training_labels().to_vec();
+        out_of_range[LABEL_OFFSET + 1] = 10;
+        assert!(matches!(
+            from_bytes(files.training_images(), &out_of_range),
+            Err(MnistError::LabelOutOfRange {
+                index: 1,
+                value: 10
+            })
+        ));
+
+        assert!(matches!(
+            from_bytes(files.training_images(), files.test_labels()),
+            Err(MnistError::CountMismatch {
+                images: 2,
+                labels: 1
+            })
+        ));
+
+        let mut wrong_dimensions = Vec::new();
+        idx::write(&mut wrong_dimensions, &[1, 2, 392], &[0u8; IMAGE_SIZE]).unwrap();
+        assert!(matches!(
+            from_bytes(&wrong_dimensions, files.test_labels()),
+            Err(MnistError::WrongDimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn validates_mnist_labels() {
+        let directory = MnistFiles::default_directory();
+        let labels =
+            read_first_existing(&directory, Mnist::file_names(FileKind::TrainingLabels)).unwrap();
+        assert_eq!(
+            validate_labels::<DigitClass>(&labels).unwrap(),
+            TrainingLabel::COUNT
+        );
+    }
+
+    /// Loaded from `$MNIST_DATASET_MISMATCHED_DIR`
+    enum Mismatched {}
+    impl Source for Mismatched {
+        type Class = DigitClass;
+        const NAME: &'static str = "mismatched";
+        const DIRECTORY_VARIABLE: &'static str = "MNIST_DATASET_MISMATCHED_DIR";
+        const DIRECTORY: &'static str = "";
+        fn file_names(kind: FileKind) -> &'static [&'static str] {
+            Mnist::file_names(kind)
+        }
+        fn slots() -> &'static Slots<Self> {
+            static SLOTS: Slots<Mismatched> = Slots::new();
+            &SLOTS
+        }
+    }
+
+    #[test]
+    fn default_files_are_validated_in_pairs() {
+        let directory = temp_directory("default_files_are_validated_in_pairs");
+        example_files().write(&directory).unwrap();
+        let mut labels = Vec::new();
+        idx::write_labels(&mut labels, [DigitClass::One, DigitClass::Two]).unwrap();
+        std::fs::write(
+            directory.join(Mnist::file_names(FileKind::TestLabels)[0]),
+            labels,
+        )
+        .unwrap();
+        std::fs::remove_file(directory.join(Mnist::file_names(FileKind::TrainingImages)[0]))
+            .unwrap();
+        // SAFETY: no other test reads or writes this variable
+        unsafe { std::env::set_var(Mismatched::DIRECTORY_VARIABLE, &directory) };
+
+        assert_eq!(SourceLabel::<Mismatched, Training>::count(), 2);
+        assert!(std::panic::catch_unwind(SourceImage::<Mismatched, Training>::count).is_err());
+        let test_count = std::panic::catch_unwind(SourceData::<Mismatched, Test>::count);
+        assert!(test_count.is_err());
+        assert!(std::panic::catch_unwind(|| Mismatched::file(FileKind::TestLabels)).is_err());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    in_memory_source!(
+        /// Its handles are used before anything is installed
+        UsedFirst,
+        |_| 1
+    );
+
+    #[test]
+    fn install_fails_after_handles_were_used() {
+        // nothing is installed and the crate directory has no files, so loading the defaults panics
+        assert!(std::panic::catch_unwind(|| UsedFirst::file(FileKind::TrainingLabels)).is_err());
+        // the handles were still bound to the default files, so installing fails
+        assert!(std::panic::catch_unwind(UsedFirst::install).is_err());
+    }
+
+    #[test]
+    fn write_then_open() {
+        let directory = temp_directory("write_then_open");
+        let files = example_files();
+        files.write(&directory).unwrap();
+        assert_eq!(MnistFiles::open(&directory).unwrap(), files);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn map_files() {
+        let directory = temp_directory("map_files");
+        let files = example_files();
+        files.write(&directory).unwrap();
+        let mapped = unsafe { MnistFiles::map(&directory) }.unwrap();
+        assert!(mapped.files.iter().all(|bytes| bytes.is_mapped()));
+        assert_eq!(mapped, files);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn open_gzip_compressed() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+
+        let directory = temp_directory("open_gzip_compressed");
+        std::fs::create_dir_all(&directory).unwrap();
+        let files = example_files();
+        for kind in FileKind::ALL {
+            let path = directory.join(format!("{}.gz", Mnist::file_names(kind)[1]));
+            let mut encoder =
+                GzEncoder::new(std::fs::File::create(path).unwrap(), Compression::default());
+            encoder.write_all(files.file(kind)).unwrap();
+            encoder.finish().unwrap();
+        }
+        assert_eq!(MnistFiles::open(&directory).unwrap(), files);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,509 @@
+//! Reading and writing files in the IDX format described at the end of the readme.
+//!
+//! Unlike [Image](crate::Image) and [Label](crate::Label) this is not limited to MNIST:
+//! any element type and any number of dimensions can be read.
+
+use crate::{ClassSet, IMAGE_HEIGHT, IMAGE_SIZE, IMAGE_WIDTH, MnistError};
+
+use core::{borrow::Borrow, marker::PhantomData};
+use std::io::{self, Write};
+
+/// The type of every element in an IDX file. Coded by the third byte of the magic number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementType {
+    UnsignedByte = 0x08,
+    SignedByte = 0x09,
+    Short = 0x0B,
+    Int = 0x0C,
+    Float = 0x0D,
+    Double = 0x0E,
+}
+impl ElementType {
+    pub const fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            0x08 => Some(Self::UnsignedByte),
+            0x09 => Some(Self::SignedByte),
+            0x0B => Some(Self::Short),
+            0x0C => Some(Self::Int),
+            0x0D => Some(Self::Float),
+            0x0E => Some(Self::Double),
+            _ => None,
+        }
+    }
+    /// The number of bytes each element takes up
+    pub const fn size(self) -> usize {
+        match self {
+            Self::UnsignedByte | Self::SignedByte => 1,
+            Self::Short => 2,
+            Self::Int | Self::Float => 4,
+            Self::Double => 8,
+        }
+    }
+}
+
+/// A type that can be read from and written to an IDX file
+pub trait Element: Copy + 'static {
+    const TYPE: ElementType;
+    /// `bytes.len() == Self::TYPE.size()` must be `true`
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+    /// Appends the `Self::TYPE.size()` big endian bytes of `self` to `bytes`
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>);
+}
+impl Element for u8 {
+    const TYPE: ElementType = ElementType::UnsignedByte;
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.push(self);
+    }
+}
+impl Element for i8 {
+    const TYPE: ElementType = ElementType::SignedByte;
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        bytes[0] as i8
+    }
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.push(self as u8);
+    }
+}
+impl Element for i16 {
+    const TYPE: ElementType = ElementType::Short;
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        Self::from_be_bytes([bytes[0], bytes[1]])
+    }
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_be_bytes());
+    }
+}
+impl Element for i32 {
+    const TYPE: ElementType = ElementType::Int;
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        Self::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_be_bytes());
+    }
+}
+impl Element for f32 {
+    const TYPE: ElementType = ElementType::Float;
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        Self::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_be_bytes());
+    }
+}
+impl Element for f64 {
+    const TYPE: ElementType = ElementType::Double;
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        Self::from_be_bytes(core::array::from_fn(|i| bytes[i]))
+    }
+    fn extend_be_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_be_bytes());
+    }
+}
+
+/// The magic number and the dimension sizes at the start of every IDX file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Header {
+    pub element_type: ElementType,
+    pub dimensions: Vec<usize>,
+}
+impl Header {
+    /// Fails if `bytes` doesn't start with a valid header
+    pub fn parse(bytes: &[u8]) -> Result<Self, MnistError> {
+        let &[a, b, c, rank, ..] = bytes else {
+            return Err(MnistError::Truncated {
+                expected: 4,
+                actual: bytes.len(),
+            });
+        };
+        let magic_number = u32::from_be_bytes([a, b, c, rank]);
+        let element_type = match (a, b, ElementType::from_byte(c)) {
+            (0, 0, Some(element_type)) => element_type,
+            _ => return Err(MnistError::UnknownMagicNumber(magic_number)),
+        };
+        let header_len = 4 + 4 * rank as usize;
+        let dimensions = bytes
+            .get(4..header_len)
+            .ok_or(MnistError::Truncated {
+                expected: header_len,
+                actual: bytes.len(),
+            })?
+            .chunks_exact(4)
+            .map(|size| u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize)
+            .collect();
+        Ok(Self {
+            element_type,
+            dimensions,
+        })
+    }
+
+    /// Returns [None] if there are more than `255` dimensions or a dimension is larger than [u32::MAX]
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut bytes = vec![0, 0, self.element_type as u8, self.rank().try_into().ok()?];
+        for &size in &self.dimensions {
+            bytes.extend(u32::try_from(size).ok()?.to_be_bytes());
+        }
+        Some(bytes)
+    }
+
+    /// The magic number this header starts with
+    pub fn magic_number(&self) -> u32 {
+        (self.element_type as u32) << 8 | self.dimensions.len() as u32
+    }
+    /// The number of dimensions
+    pub fn rank(&self) -> usize {
+        self.dimensions.len()
+    }
+    /// The number of bytes taken up by this header. The data starts at this offset
+    pub fn data_offset(&self) -> usize {
+        4 + 4 * self.rank()
+    }
+    /// The product of all dimension sizes
+    pub fn element_count(&self) -> usize {
+        self.dimensions.iter().product()
+    }
+    /// The number of bytes the data after this header takes up
+    pub fn data_len(&self) -> usize {
+        self.element_count() * self.element_type.size()
+    }
+}
+
+/// A parsed IDX file borrowing its data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Idx<'a> {
+    header: Header,
+    data: &'a [u8],
+}
+impl<'a> Idx<'a> {
+    /// Fails if `bytes` doesn't start with a valid header
+    /// or if the data after the header isn't exactly as long as the header specifies
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, MnistError> {
+        let header = Header::parse(bytes)?;
+        let data = &bytes[header.data_offset()..];
+        let expected = header
+            .dimensions
+            .iter()
+            .try_fold(header.element_type.size(), |len, &size| {
+                len.checked_mul(size)
+            })
+            .and_then(|data_len| data_len.checked_add(header.data_offset()))
+            .unwrap_or(usize::MAX);
+        match bytes.len() {
+            actual if actual < expected => Err(MnistError::Truncated { expected, actual }),
+            actual if actual > expected => Err(MnistError::TrailingData { expected, actual }),
+            _ => Ok(Self { header, data }),
+        }
+    }
+
+    /// Fails with [MnistError::BadMagicNumber] if this file doesn't start with `expected`
+    pub fn expect_magic_number(&self, expected: u32) -> Result<(), MnistError> {
+        match self.header.magic_number() {
+            actual if actual == expected => Ok(()),
+            actual => Err(MnistError::BadMagicNumber { expected, actual }),
+        }
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    pub fn element_type(&self) -> ElementType {
+        self.header.element_type
+    }
+    pub fn dimensions(&self) -> &[usize] {
+        &self.header.dimensions
+    }
+    /// The big endian bytes after the header
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns [None] if the elements of this file are not `T`s
+    pub fn view<T: Element>(&self) -> Option<View<'a, T>> {
+        (T::TYPE == self.element_type()).then(|| View {
+            dimensions: self.header.dimensions.clone(),
+            data: self.data,
+            element_type: PhantomData,
+        })
+    }
+}
+
+/// An n-dimensional view of elements of type `T`.
+/// The data is stored like in a C array, the index in the last dimension changes the fastest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View<'a, T: Element> {
+    dimensions: Vec<usize>,
+    data: &'a [u8],
+    element_type: PhantomData<T>,
+}
+impl<'a, T: Element> View<'a, T> {
+    pub fn dimensions(&self) -> &[usize] {
+        &self.dimensions
+    }
+    pub fn rank(&self) -> usize {
+        self.dimensions.len()
+    }
+    /// The size of the first dimension. `1` for a rank `0` view
+    pub fn len(&self) -> usize {
+        self.dimensions.first().copied().unwrap_or(1)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns [None] if `index` doesn't have one entry per dimension or is out of bounds
+    pub fn get(&self, index: &[usize]) -> Option<T> {
+        if index.len() != self.rank() {
+            return None;
+        }
+        let mut offset = 0;
+        for (&i, &size) in index.iter().zip(&self.dimensions) {
+            if i >= size {
+                return None;
+            }
+            offset = offset * size + i;
+        }
+        let size = T::TYPE.size();
+        Some(T::from_be_bytes(
+            &self.data[offset * size..(offset + 1) * size],
+        ))
+    }
+
+    /// The `(rank - 1)` dimensional view at `index` in the first dimension.
+    /// Returns [None] if `index` is out of bounds or this view has rank `0`
+    pub fn item(&self, index: usize) -> Option<Self> {
+        let (&first, rest) = self.dimensions.split_first()?;
+        if index >= first {
+            return None;
+        }
+        let item_len = rest.iter().product::<usize>() * T::TYPE.size();
+        Some(Self {
+            dimensions: rest.to_vec(),
+            data: &self.data[index * item_len..(index + 1) * item_len],
+            element_type: PhantomData,
+        })
+    }
+    /// Each `(rank - 1)` dimensional view along the first dimension
+    pub fn items(&self) -> impl Iterator<Item = Self> + '_ {
+        (0..self.len()).filter_map(|index| self.item(index))
+    }
+
+    /// Every element in C array order
+    pub fn iter(&self) -> impl Iterator<Item = T> + 'a {
+        self.data.chunks_exact(T::TYPE.size()).map(T::from_be_bytes)
+    }
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
+    }
+
+    #[cfg(feature = "ndarray")]
+    pub fn to_array(&self) -> ndarray::ArrayD<T> {
+        ndarray::ArrayD::from_shape_vec(self.dimensions.as_slice(), self.to_vec())
+            .expect("the number of elements is the product of the dimensions")
+    }
+}
+impl<'a> View<'a, u8> {
+    /// Unsigned bytes are stored as is, so they can be borrowed
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// Writes `elements` as an IDX file with the given `dimensions`.
+///
+/// Fails with [io::ErrorKind::InvalidInput] if the number of `elements` isn't the product of `dimensions`
+/// or if the `dimensions` can't be stored in a [Header]
+pub fn write<T: Element>(
+    mut writer: impl Write,
+    dimensions: &[usize],
+    elements: &[T],
+) -> io::Result<()> {
+    if elements.len() != dimensions.iter().product::<usize>() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} elements don't fit dimensions {dimensions:?}",
+                elements.len()
+            ),
+        ));
+    }
+    let header = Header {
+        element_type: T::TYPE,
+        dimensions: dimensions.to_vec(),
+    };
+    let mut bytes = header.to_bytes().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("dimensions {dimensions:?} can't be stored in an IDX header"),
+        )
+    })?;
+    bytes.reserve(elements.len() * T::TYPE.size());
+    for &element in elements {
+        element.extend_be_bytes(&mut bytes);
+    }
+    writer.write_all(&bytes)
+}
+
+/// Writes `images` in the same format as `train-images.idx3-ubyte`.
+///
+/// Accepts owned `[u8; IMAGE_SIZE]` buffers or the `&[u8; IMAGE_SIZE]` given by [Image::as_bytes](crate::Image::as_bytes)
+pub fn write_images<I: Borrow<[u8; IMAGE_SIZE]>>(
+    writer: impl Write,
+    images: impl IntoIterator<Item = I>,
+) -> io::Result<()> {
+    let data = images
+        .into_iter()
+        .flat_map(|image| *image.borrow())
+        .collect::<Vec<u8>>();
+    write(
+        writer,
+        &[data.len() / IMAGE_SIZE, IMAGE_HEIGHT, IMAGE_WIDTH],
+        &data,
+    )
+}
+
+/// Writes `labels` in the same format as `train-labels.idx1-ubyte`
+pub fn write_labels<C: ClassSet>(
+    writer: impl Write,
+    labels: impl IntoIterator<Item = C>,
+) -> io::Result<()> {
+    let data = labels
+        .into_iter()
+        .map(ClassSet::to_byte)
+        .collect::<Vec<u8>>();
+    write(writer, &[data.len()], &data)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        IMAGE_HEIGHT, IMAGE_MAGIC_NUMBER, IMAGE_OFFSET, IMAGE_SIZE, IMAGE_WIDTH,
+        LABEL_MAGIC_NUMBER, Label, MnistError, TestLabel,
+        idx::{self, ElementType, Header, Idx},
+    };
+
+    #[test]
+    fn parses_header() {
+        let bytes = [0, 0, 0x08, 3, 0, 0, 0, 2, 0, 0, 0, 28, 0, 0, 0, 28];
+        let header = Header::parse(&bytes).unwrap();
+        assert_eq!(header.element_type, ElementType::UnsignedByte);
+        assert_eq!(header.dimensions, [2, IMAGE_HEIGHT, IMAGE_WIDTH]);
+        assert_eq!(header.magic_number(), IMAGE_MAGIC_NUMBER);
+        assert_eq!(header.data_offset(), IMAGE_OFFSET);
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(matches!(
+            Header::parse(&[1, 0, 0x08, 1, 0, 0, 0, 0]),
+            Err(MnistError::UnknownMagicNumber(0x01000801))
+        ));
+        assert!(matches!(
+            Header::parse(&[0, 0, 0x0A, 1, 0, 0, 0, 0]),
+            Err(MnistError::UnknownMagicNumber(0x00000A01))
+        ));
+        assert!(matches!(
+            Header::parse(&[0, 0, 0x08, 2, 0, 0, 0, 0]),
+            Err(MnistError::Truncated {
+                expected: 12,
+                actual: 8
+            })
+        ));
+        assert!(matches!(
+            Idx::parse(&[0, 0, 0x08, 1, 0, 0, 0, 2, 7]),
+            Err(MnistError::Truncated {
+                expected: 10,
+                actual: 9
+            })
+        ));
+        assert!(matches!(
+            Idx::parse(&[0, 0, 0x08, 1, 0, 0, 0, 1, 7, 7]),
+            Err(MnistError::TrailingData {
+                expected: 9,
+                actual: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn reads_every_element_type() {
+        let shorts = [
+            0, 0, 0x0B, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0xFF, 0xFE, 0x01, 0x00,
+        ];
+        let shorts = Idx::parse(&shorts).unwrap().view::<i16>().unwrap();
+        assert_eq!(shorts.get(&[0, 0]), Some(-2));
+        assert_eq!(shorts.get(&[1, 0]), Some(256));
+        assert_eq!(shorts.get(&[1, 1]), None);
+
+        let mut doubles = vec![0, 0, 0x0E, 1, 0, 0, 0, 1];
+        doubles.extend(1.5f64.to_be_bytes());
+        let doubles = Idx::parse(&doubles).unwrap();
+        assert!(doubles.view::<f32>().is_none());
+        assert_eq!(doubles.view::<f64>().unwrap().to_vec(), [1.5]);
+
+        let signed = Idx::parse(&[0, 0, 0x09, 1, 0, 0, 0, 1, 0x80]).unwrap();
+        assert_eq!(signed.view::<i8>().unwrap().get(&[0]), Some(i8::MIN));
+    }
+
+    #[test]
+    fn items_slice_the_first_dimension() {
+        let ints = [
+            0, 0, 0x0C, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 4,
+        ];
+        let ints = Idx::parse(&ints).unwrap().view::<i32>().unwrap();
+        let items = ints.items().map(|item| item.to_vec()).collect::<Vec<_>>();
+        assert_eq!(items, [[3], [4]]);
+        assert_eq!(ints.item(1).unwrap().item(0).unwrap().get(&[]), Some(4));
+    }
+
+    #[test]
+    fn reads_mnist_labels() {
+        let labels = Idx::parse(TestLabel::raw_data()).unwrap();
+        assert_eq!(labels.header().magic_number(), LABEL_MAGIC_NUMBER);
+        let labels = labels.view::<u8>().unwrap();
+        assert_eq!(labels.len(), TestLabel::COUNT);
+        for label in TestLabel::all().step_by(97) {
+            assert_eq!(
+                labels.get(&[label.index()]),
+                Some(label.digit_class() as u8)
+            );
+        }
+    }
+
+    #[test]
+    fn write_then_parse() {
+        let mut bytes = Vec::new();
+        idx::write(&mut bytes, &[2, 3], &[1.0f32, -2.0, 3.5, 0.0, 0.25, 6.0]).unwrap();
+        let floats = Idx::parse(&bytes).unwrap().view::<f32>().unwrap();
+        assert_eq!(floats.dimensions(), [2, 3]);
+        assert_eq!(floats.get(&[1, 1]), Some(0.25));
+
+        let error = idx::write(&mut Vec::new(), &[2, 2], &[1u8, 2, 3]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rewrites_mnist_labels() {
+        let mut bytes = Vec::new();
+        idx::write_labels(
+            &mut bytes,
+            TestLabel::all().map(|label| label.digit_class()),
+        )
+        .unwrap();
+        assert_eq!(bytes, TestLabel::raw_data());
+    }
+
+    #[test]
+    fn writes_images() {
+        let images = [[0; IMAGE_SIZE], [255; IMAGE_SIZE]];
+        let mut bytes = Vec::new();
+        idx::write_images(&mut bytes, images).unwrap();
+        let idx = Idx::parse(&bytes).unwrap();
+        assert_eq!(idx.header().magic_number(), IMAGE_MAGIC_NUMBER);
+        let view = idx.view::<u8>().unwrap();
+        assert_eq!(view.dimensions(), [2, IMAGE_HEIGHT, IMAGE_WIDTH]);
+        assert_eq!(view.item(1).unwrap().as_slice(), images[1]);
+    }
+}
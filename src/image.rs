@@ -1,3 +1,13 @@
+use crate::{
+    MnistError,
+    files::{Mnist, Source, Split, Test, Training},
+    idx::Idx,
+};
+
+use core::{borrow::Borrow, fmt, marker::PhantomData};
+#[cfg(feature = "ndarray")]
+use ndarray::{ArrayView2, ArrayView3};
+
 /// The image data starts at byte `16` for the [TrainingLabel]s and [TestLabel]s
 pub const IMAGE_OFFSET: usize = 16;
 
@@ -13,6 +23,19 @@ pub const IMAGE_SIZE: usize = IMAGE_WIDTH * IMAGE_HEIGHT;
 /// Each images file starts with `2051`
 pub const IMAGE_MAGIC_NUMBER: u32 = 2051;
 
+/// Checks that `bytes` is a complete images file of `28x28` images. Returns the number of images
+pub fn validate_images(bytes: &[u8]) -> Result<usize, MnistError> {
+    let idx = Idx::parse(bytes)?;
+    idx.expect_magic_number(IMAGE_MAGIC_NUMBER)?;
+    match *idx.dimensions() {
+        [count, IMAGE_HEIGHT, IMAGE_WIDTH] => Ok(count),
+        ref actual => Err(MnistError::WrongDimensions {
+            expected: vec![actual[0], IMAGE_HEIGHT, IMAGE_WIDTH],
+            actual: actual.to_vec(),
+        }),
+    }
+}
+
 /// Returns `(start, end)`
 pub const fn calculate_image_bounds(image_index: usize) -> (usize, usize) {
     let start = IMAGE_OFFSET + (image_index * IMAGE_SIZE);
@@ -20,6 +43,21 @@ pub const fn calculate_image_bounds(image_index: usize) -> (usize, usize) {
     (start, end)
 }
 
+pub(crate) const fn u32_from_big_endian_bytes(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8 | (bytes[3] as u32)
+}
+
+/// Swaps the rows and columns of every image in an images file
+pub(crate) fn transpose_images(bytes: &mut [u8]) {
+    for image in bytes[IMAGE_OFFSET..].as_chunks_mut::<IMAGE_SIZE>().0 {
+        for y in 0..IMAGE_HEIGHT {
+            for x in y + 1..IMAGE_WIDTH {
+                image.swap(y * IMAGE_WIDTH + x, x * IMAGE_WIDTH + y);
+            }
+        }
+    }
+}
+
 fn normalize_byte(b: u8) -> f32 {
     b as f32 / u8::MAX as f32
 }
@@ -30,22 +68,25 @@ pub(super) fn normalize_bytes<const N: usize>(bytes: &[u8; N]) -> [f32; N] {
 
 /// A handle to a specific image from the MNIST dataset
 pub trait Image: Sized {
-    const RAW_DATA: &[u8];
-    const COUNT: usize;
-    /// # Safety:
-    /// `index < <Self as Image>::COUNT` must be `true`
+    /// Contents of the images file this handle indexes into
+    fn raw_data() -> &'static [u8];
+    /// # Safety
+    /// `index < <Self as Image>::count()` must be `true`
     unsafe fn from_index_unchecked(index: usize) -> Self;
     fn index(&self) -> usize;
 
+    /// The number of images as stored in the header of [Image::raw_data]
+    fn count() -> usize {
+        u32_from_big_endian_bytes(&Self::raw_data()[4..8]) as usize
+    }
+
     fn from_index(index: usize) -> Option<Self> {
-        (index < Self::COUNT).then(|| unsafe { Self::from_index_unchecked(index) })
+        (index < Self::count()).then(|| unsafe { Self::from_index_unchecked(index) })
     }
 
     fn as_bytes(&self) -> &'static [u8; IMAGE_SIZE] {
-        let (start, end) = calculate_image_bounds(self.index());
-        Self::RAW_DATA[start..end]
-            .try_into()
-            .expect("end - start == IMAGE_SIZE")
+        let (images, _) = Self::raw_data()[IMAGE_OFFSET..].as_chunks::<IMAGE_SIZE>();
+        &images[self.index()]
     }
     fn to_array(&self) -> [u8; IMAGE_SIZE] {
         self.as_bytes().to_owned()
@@ -53,44 +94,232 @@ pub trait Image: Sized {
     fn to_vec(&self) -> Vec<u8> {
         self.as_bytes().to_vec()
     }
+    /// Shape = `(IMAGE_HEIGHT, IMAGE_WIDTH)`, borrowed from [Image::raw_data]
+    #[cfg(feature = "ndarray")]
+    fn view(&self) -> ArrayView2<'static, u8> {
+        ArrayView2::from_shape((IMAGE_HEIGHT, IMAGE_WIDTH), self.as_bytes())
+            .expect("every image has IMAGE_SIZE bytes")
+    }
 
     fn all() -> impl Iterator<Item = Self> {
-        (0..Self::COUNT).filter_map(Self::from_index)
+        (0..Self::count()).filter_map(Self::from_index)
+    }
+    /// Shape = `(Self::count(), IMAGE_HEIGHT, IMAGE_WIDTH)`, every image borrowed from [Image::raw_data]
+    #[cfg(feature = "ndarray")]
+    fn view_all() -> ArrayView3<'static, u8> {
+        ArrayView3::from_shape(
+            (Self::count(), IMAGE_HEIGHT, IMAGE_WIDTH),
+            &Self::raw_data()[IMAGE_OFFSET..],
+        )
+        .expect("images are validated when they are loaded")
+    }
+    /// Shape = `(Self::count(), IMAGE_SIZE)`, one image per row borrowed from [Image::raw_data]
+    #[cfg(feature = "ndarray")]
+    fn view_all_flat() -> ArrayView2<'static, u8> {
+        ArrayView2::from_shape(
+            (Self::count(), IMAGE_SIZE),
+            &Self::raw_data()[IMAGE_OFFSET..],
+        )
+        .expect("images are validated when they are loaded")
     }
 }
 
-/// A handle to a specific test [Image] from the MNIST dataset
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct TestImage(usize);
-impl Image for TestImage {
-    /// Contents of `t10k-images.idx3-ubyte`
-    const RAW_DATA: &[u8] = include_bytes!("../dataset/t10k-images.idx3-ubyte");
-
-    /// The number of test images in MNIST
-    const COUNT: usize = 10000;
+/// A handle to a specific image of the [Split] `P` of the [Source] `S`
+pub struct SourceImage<S, P>(usize, PhantomData<fn() -> (S, P)>);
+impl<S: Source, P: Split> Image for SourceImage<S, P> {
+    fn raw_data() -> &'static [u8] {
+        S::file(P::IMAGES)
+    }
 
     unsafe fn from_index_unchecked(index: usize) -> Self {
-        Self(index)
+        Self(index, PhantomData)
     }
     fn index(&self) -> usize {
         self.0
     }
 }
+impl<S, P> fmt::Debug for SourceImage<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SourceImage").field(&self.0).finish()
+    }
+}
+impl<S, P> Clone for SourceImage<S, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<S, P> Copy for SourceImage<S, P> {}
+impl<S, P> PartialEq for SourceImage<S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<S, P> Eq for SourceImage<S, P> {}
 
-/// A handle to a specific training [Image] from the MNIST dataset
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct TrainingImage(usize);
-impl Image for TrainingImage {
-    /// Contents of `train-images.idx3-ubyte`
-    const RAW_DATA: &[u8] = include_bytes!("../dataset/train-images.idx3-ubyte");
+/// A handle to a specific test [Image] from the MNIST dataset
+pub type TestImage = SourceImage<Mnist, Test>;
+impl TestImage {
+    /// The number of test images in MNIST
+    pub const COUNT: usize = 10000;
+}
 
+/// A handle to a specific training [Image] from the MNIST dataset
+pub type TrainingImage = SourceImage<Mnist, Training>;
+impl TrainingImage {
     /// The number of training images in MNIST
-    const COUNT: usize = 60000;
+    pub const COUNT: usize = 60000;
+}
 
-    unsafe fn from_index_unchecked(index: usize) -> Self {
-        Self(index)
+/// An owned `28x28` image, e.g. a modified copy of an [Image] handle or a drawing.
+/// Pixels are indexed by column `x` and row `y` starting from the top left
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GrayImage28([u8; IMAGE_SIZE]);
+impl GrayImage28 {
+    /// `bytes` are row major
+    pub const fn new(bytes: [u8; IMAGE_SIZE]) -> Self {
+        Self(bytes)
     }
-    fn index(&self) -> usize {
+    /// Every pixel is `0`, the background
+    pub const fn blank() -> Self {
+        Self([0; IMAGE_SIZE])
+    }
+    /// The pixel at `(x, y)` is `f(x, y)`
+    pub fn from_fn(mut f: impl FnMut(usize, usize) -> u8) -> Self {
+        Self(core::array::from_fn(|i| {
+            f(i % IMAGE_WIDTH, i / IMAGE_WIDTH)
+        }))
+    }
+
+    /// Returns [None] if `x >= IMAGE_WIDTH` or `y >= IMAGE_HEIGHT`
+    pub fn get(&self, x: usize, y: usize) -> Option<u8> {
+        (x < IMAGE_WIDTH && y < IMAGE_HEIGHT).then(|| self.0[y * IMAGE_WIDTH + x])
+    }
+    /// Returns [None] if `x >= IMAGE_WIDTH` or `y >= IMAGE_HEIGHT`
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut u8> {
+        (x < IMAGE_WIDTH && y < IMAGE_HEIGHT).then(|| &mut self.0[y * IMAGE_WIDTH + x])
+    }
+
+    /// The row major bytes
+    pub const fn as_bytes(&self) -> &[u8; IMAGE_SIZE] {
+        &self.0
+    }
+    pub const fn as_bytes_mut(&mut self) -> &mut [u8; IMAGE_SIZE] {
+        &mut self.0
+    }
+    pub const fn into_bytes(self) -> [u8; IMAGE_SIZE] {
         self.0
     }
+    pub fn normalized(&self) -> [f32; IMAGE_SIZE] {
+        normalize_bytes(&self.0)
+    }
+
+    /// From top to bottom
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[u8; IMAGE_WIDTH]> {
+        self.0.as_chunks::<IMAGE_WIDTH>().0.iter()
+    }
+    /// From left to right, each from top to bottom
+    pub fn columns(&self) -> impl ExactSizeIterator<Item = [u8; IMAGE_HEIGHT]> + '_ {
+        (0..IMAGE_WIDTH).map(|x| core::array::from_fn(|y| self.0[y * IMAGE_WIDTH + x]))
+    }
+
+    /// Applies `f` to every pixel
+    pub fn map(self, mut f: impl FnMut(u8) -> u8) -> Self {
+        Self(self.0.map(&mut f))
+    }
+    /// Swaps rows and columns
+    pub fn transposed(&self) -> Self {
+        Self::from_fn(|x, y| self.0[x * IMAGE_WIDTH + y])
+    }
+    /// Mirrors left and right
+    pub fn flipped_horizontally(&self) -> Self {
+        Self::from_fn(|x, y| self.0[y * IMAGE_WIDTH + IMAGE_WIDTH - 1 - x])
+    }
+    /// Mirrors top and bottom
+    pub fn flipped_vertically(&self) -> Self {
+        Self::from_fn(|x, y| self.0[(IMAGE_HEIGHT - 1 - y) * IMAGE_WIDTH + x])
+    }
+
+    /// Shape = `(IMAGE_HEIGHT, IMAGE_WIDTH)`
+    #[cfg(feature = "ndarray")]
+    pub fn view(&self) -> ArrayView2<'_, u8> {
+        ArrayView2::from_shape((IMAGE_HEIGHT, IMAGE_WIDTH), &self.0)
+            .expect("every image has IMAGE_SIZE bytes")
+    }
+    /// Returns [None] if the shape of `view` isn't `(IMAGE_HEIGHT, IMAGE_WIDTH)`
+    #[cfg(feature = "ndarray")]
+    pub fn from_view(view: ArrayView2<'_, u8>) -> Option<Self> {
+        (view.dim() == (IMAGE_HEIGHT, IMAGE_WIDTH)).then(|| {
+            let mut pixels = view.iter().copied();
+            Self::from_fn(|_, _| pixels.next().expect("the shape was checked"))
+        })
+    }
+}
+impl Default for GrayImage28 {
+    fn default() -> Self {
+        Self::blank()
+    }
+}
+impl<I: Image> From<I> for GrayImage28 {
+    fn from(image: I) -> Self {
+        Self(image.to_array())
+    }
+}
+impl From<[u8; IMAGE_SIZE]> for GrayImage28 {
+    fn from(bytes: [u8; IMAGE_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+impl From<GrayImage28> for [u8; IMAGE_SIZE] {
+    fn from(image: GrayImage28) -> Self {
+        image.0
+    }
+}
+impl AsRef<[u8; IMAGE_SIZE]> for GrayImage28 {
+    fn as_ref(&self) -> &[u8; IMAGE_SIZE] {
+        &self.0
+    }
+}
+impl Borrow<[u8; IMAGE_SIZE]> for GrayImage28 {
+    fn borrow(&self) -> &[u8; IMAGE_SIZE] {
+        &self.0
+    }
+}
+impl fmt::Debug for GrayImage28 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.rows()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GrayImage28, IMAGE_WIDTH, Image, TestImage};
+
+    #[test]
+    fn gray_image_accessors() {
+        let image = GrayImage28::from_fn(|x, y| (y * IMAGE_WIDTH + x) as u8);
+        assert_eq!(image.get(3, 1), Some(31));
+        assert_eq!(image.get(28, 0), None);
+        assert_eq!(image.rows().nth(1).unwrap()[3], 31);
+        assert_eq!(image.columns().nth(3).unwrap()[1], 31);
+        assert_eq!(image.transposed().get(1, 3), Some(31));
+        assert_eq!(image.transposed().transposed(), image);
+        assert_eq!(image.flipped_horizontally().get(24, 1), Some(31));
+        assert_eq!(image.flipped_vertically().get(3, 26), Some(31));
+
+        let mut inverted = image.map(|byte| !byte);
+        *inverted.get_mut(3, 1).unwrap() = 0;
+        assert_eq!(inverted.get(3, 1), Some(0));
+        assert_eq!(inverted.get(4, 1), Some(!32));
+    }
+
+    #[test]
+    fn gray_image_from_handle() {
+        let handle = TestImage::from_index(42).unwrap();
+        let image = GrayImage28::from(handle);
+        assert_eq!(image.as_bytes(), handle.as_bytes());
+        assert_eq!(
+            image.get(14, 10),
+            Some(handle.as_bytes()[10 * IMAGE_WIDTH + 14])
+        );
+    }
 }
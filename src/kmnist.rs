@@ -0,0 +1,125 @@
+//! [Kuzushiji-MNIST](https://github.com/rois-codh/kmnist): 28x28 gray-scale images of ten cursive Japanese
+//! hiragana stored in the same IDX layout as MNIST
+
+use crate::{
+    ClassSet, Files, SourceData, SourceImage, SourceLabel, Test, Training,
+    files::{FileKind, Slots, Source},
+};
+
+/// The Kuzushiji-MNIST dataset.
+/// Read from `dataset/kmnist/` or `$KMNIST_DATASET_DIR` by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kmnist {}
+impl Source for Kmnist {
+    type Class = KmnistClass;
+    const NAME: &'static str = "Kuzushiji-MNIST";
+    const DIRECTORY_VARIABLE: &'static str = "KMNIST_DATASET_DIR";
+    const DIRECTORY: &'static str = "dataset/kmnist";
+
+    /// The first name is the one used by the original distribution
+    fn file_names(kind: FileKind) -> &'static [&'static str] {
+        match kind {
+            FileKind::TrainingImages => &["train-images-idx3-ubyte", "train-images.idx3-ubyte"],
+            FileKind::TrainingLabels => &["train-labels-idx1-ubyte", "train-labels.idx1-ubyte"],
+            FileKind::TestImages => &["t10k-images-idx3-ubyte", "t10k-images.idx3-ubyte"],
+            FileKind::TestLabels => &["t10k-labels-idx1-ubyte", "t10k-labels.idx1-ubyte"],
+        }
+    }
+
+    fn slots() -> &'static Slots<Self> {
+        static SLOTS: Slots<Kmnist> = Slots::new();
+        &SLOTS
+    }
+}
+
+/// The possible classes of hiragana in the Kuzushiji-MNIST dataset, named by their romanization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KmnistClass {
+    O = 0,
+    Ki = 1,
+    Su = 2,
+    Tsu = 3,
+    Na = 4,
+    Ha = 5,
+    Ma = 6,
+    Ya = 7,
+    Re = 8,
+    Wo = 9,
+}
+impl KmnistClass {
+    /// The number of classes of hiragana
+    pub const COUNT: usize = 10;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::O,
+        Self::Ki,
+        Self::Su,
+        Self::Tsu,
+        Self::Na,
+        Self::Ha,
+        Self::Ma,
+        Self::Ya,
+        Self::Re,
+        Self::Wo,
+    ];
+    pub const fn from_byte(b: u8) -> Option<Self> {
+        if (b as usize) < Self::COUNT {
+            Some(Self::ALL[b as usize])
+        } else {
+            None
+        }
+    }
+    /// The modern hiragana, as listed in the `classmap.csv` of the original distribution
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::O => "お",
+            Self::Ki => "き",
+            Self::Su => "す",
+            Self::Tsu => "つ",
+            Self::Na => "な",
+            Self::Ha => "は",
+            Self::Ma => "ま",
+            Self::Ya => "や",
+            Self::Re => "れ",
+            Self::Wo => "を",
+        }
+    }
+    /// see https://en.wikipedia.org/wiki/One-hot
+    pub const fn one_hot_encode(self) -> [f32; Self::COUNT] {
+        let mut encoded = [0.0; Self::COUNT];
+        encoded[self as usize] = 1.0;
+        encoded
+    }
+}
+impl From<KmnistClass> for usize {
+    fn from(value: KmnistClass) -> Self {
+        value as usize
+    }
+}
+
+impl ClassSet for KmnistClass {
+    const COUNT: usize = Self::COUNT;
+    fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+    fn index(self) -> usize {
+        self as usize
+    }
+    fn name(self) -> &'static str {
+        KmnistClass::name(self)
+    }
+}
+
+/// The contents of the four Kuzushiji-MNIST files, read at runtime
+pub type KmnistFiles = Files<Kmnist>;
+
+/// A handle to a specific training [Image](crate::Image) from the Kuzushiji-MNIST dataset
+pub type KmnistTrainingImage = SourceImage<Kmnist, Training>;
+/// A handle to a specific training [Label](crate::Label) from the Kuzushiji-MNIST dataset
+pub type KmnistTrainingLabel = SourceLabel<Kmnist, Training>;
+/// A handle to a specific test [Image](crate::Image) from the Kuzushiji-MNIST dataset
+pub type KmnistTestImage = SourceImage<Kmnist, Test>;
+/// A handle to a specific test [Label](crate::Label) from the Kuzushiji-MNIST dataset
+pub type KmnistTestLabel = SourceLabel<Kmnist, Test>;
+
+pub type KmnistTrainingData = SourceData<Kmnist, Training>;
+pub type KmnistTestData = SourceData<Kmnist, Test>;
@@ -1,4 +1,55 @@
-use crate::{Image, TestImage, TrainingImage};
+use crate::{
+    MnistError, TestImage, TrainingImage,
+    files::{Mnist, Source, Split, Test, Training},
+    idx::Idx,
+    image::u32_from_big_endian_bytes,
+};
+
+use core::{fmt, marker::PhantomData};
+
+/// A set of classes that a [Label] can specify, e.g. [DigitClass]
+pub trait ClassSet: Copy + Eq + fmt::Debug + Send + Sync + 'static {
+    /// The number of classes
+    const COUNT: usize;
+    /// Returns [None] if `index >= Self::COUNT`
+    fn from_index(index: usize) -> Option<Self>;
+    /// In `0..Self::COUNT`
+    fn index(self) -> usize;
+    /// A human readable name
+    fn name(self) -> &'static str;
+
+    /// The class stored as `b` in a labels file. Most datasets store [ClassSet::index]
+    fn from_byte(b: u8) -> Option<Self> {
+        Self::from_index(b as usize)
+    }
+    /// The byte that stores this class in a labels file
+    fn to_byte(self) -> u8 {
+        self.index() as u8
+    }
+
+    /// `Self::COUNT` elements long. see https://en.wikipedia.org/wiki/One-hot
+    fn one_hot_encode(self) -> Vec<f32> {
+        let mut encoded = vec![0.0; Self::COUNT];
+        encoded[self.index()] = 1.0;
+        encoded
+    }
+    /// The class with the largest value in `encoded`, e.g. the output of a classifier.
+    /// Returns [None] if `encoded` isn't `Self::COUNT` elements long
+    fn one_hot_decode(encoded: &[f32]) -> Option<Self> {
+        if encoded.len() != Self::COUNT {
+            return None;
+        }
+        let (index, _) = encoded
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        Self::from_index(index)
+    }
+
+    fn all() -> impl Iterator<Item = Self> {
+        (0..Self::COUNT).filter_map(Self::from_index)
+    }
+}
 
 /// The possible classes of digits in the MNIST dataset
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -60,6 +111,18 @@ impl DigitClass {
     pub const EIGHT: [f32; DigitClass::COUNT] = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0];
     pub const NINE: [f32; DigitClass::COUNT] = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
 }
+impl ClassSet for DigitClass {
+    const COUNT: usize = Self::COUNT;
+    fn from_index(index: usize) -> Option<Self> {
+        Self::from_byte(index.try_into().ok()?)
+    }
+    fn index(self) -> usize {
+        self as usize
+    }
+    fn name(self) -> &'static str {
+        ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"][self as usize]
+    }
+}
 impl From<DigitClass> for usize {
     fn from(value: DigitClass) -> Self {
         value as usize
@@ -87,62 +150,101 @@ pub const LABEL_OFFSET: usize = 8;
 /// Each labels file starts with `2049`
 pub const LABEL_MAGIC_NUMBER: u32 = 2049;
 
+/// Checks that `bytes` is a complete labels file where every label is a `C`. Returns the number of labels
+pub fn validate_labels<C: ClassSet>(bytes: &[u8]) -> Result<usize, MnistError> {
+    let idx = Idx::parse(bytes)?;
+    idx.expect_magic_number(LABEL_MAGIC_NUMBER)?;
+    match idx.data().iter().position(|&b| C::from_byte(b).is_none()) {
+        Some(index) => Err(MnistError::LabelOutOfRange {
+            index,
+            value: idx.data()[index].into(),
+        }),
+        None => Ok(idx.data().len()),
+    }
+}
+
 /// A handle to a specific label from the MNIST dataset
 pub trait Label: Sized {
-    const RAW_DATA: &[u8];
-    const COUNT: usize;
-    /// # Safety:
-    /// `index < <Self as Image>::COUNT` must be `true`
+    /// The classes this label can specify
+    type Class: ClassSet;
+    /// Contents of the labels file this handle indexes into
+    fn raw_data() -> &'static [u8];
+    /// # Safety
+    /// `index < <Self as Label>::count()` must be `true`
     unsafe fn from_index_unchecked(index: usize) -> Self;
     fn index(&self) -> usize;
 
+    /// The number of labels as stored in the header of [Label::raw_data]
+    fn count() -> usize {
+        u32_from_big_endian_bytes(&Self::raw_data()[4..8]) as usize
+    }
+
     fn from_index(index: usize) -> Option<Self> {
-        (index < Self::COUNT).then(|| unsafe { Self::from_index_unchecked(index) })
+        (index < Self::count()).then(|| unsafe { Self::from_index_unchecked(index) })
     }
 
-    fn digit_class(&self) -> DigitClass {
+    /// The class specified by this label handle
+    fn class(&self) -> Self::Class {
         let index = self.index() + LABEL_OFFSET;
-        DigitClass::from_byte(Self::RAW_DATA[index])
-            .expect("all bytes after LABEL_OFFSET are in range 0..=9")
+        Self::Class::from_byte(Self::raw_data()[index])
+            .expect("labels are validated when they are loaded")
+    }
+    fn digit_class(&self) -> DigitClass
+    where
+        Self: Label<Class = DigitClass>,
+    {
+        self.class()
     }
 
     fn all() -> impl Iterator<Item = Self> {
-        (0..Self::COUNT).filter_map(Self::from_index)
+        (0..Self::count()).filter_map(Self::from_index)
     }
 }
 
-/// A handle to a specific test [Label] from the MNIST dataset
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct TestLabel(usize);
-impl Label for TestLabel {
-    /// Contents of `t10k-labels.idx1-ubyte`
-    const RAW_DATA: &[u8] = include_bytes!("../dataset/t10k-labels.idx1-ubyte");
+/// A handle to a specific label of the [Split] `P` of the [Source] `S`
+pub struct SourceLabel<S, P>(usize, PhantomData<fn() -> (S, P)>);
+impl<S: Source, P: Split> Label for SourceLabel<S, P> {
+    type Class = S::Class;
 
-    /// The number of test labels in MNIST. equal to [TestImage::COUNT]
-    const COUNT: usize = TestImage::COUNT;
+    fn raw_data() -> &'static [u8] {
+        S::file(P::LABELS)
+    }
 
     unsafe fn from_index_unchecked(index: usize) -> Self {
-        Self(index)
+        Self(index, PhantomData)
     }
     fn index(&self) -> usize {
         self.0
     }
 }
-
-/// A handle to a specific training [Label] from the MNIST dataset
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct TrainingLabel(usize);
-impl Label for TrainingLabel {
-    /// Contents of `train-labels.idx1-ubyte"`
-    const RAW_DATA: &[u8] = include_bytes!("../dataset/train-labels.idx1-ubyte");
-
-    /// The number of training labels in MNIST. equal to [TestImage::COUNT]
-    const COUNT: usize = TrainingImage::COUNT;
-
-    unsafe fn from_index_unchecked(index: usize) -> Self {
-        Self(index)
+impl<S, P> fmt::Debug for SourceLabel<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SourceLabel").field(&self.0).finish()
     }
-    fn index(&self) -> usize {
-        self.0
+}
+impl<S, P> Clone for SourceLabel<S, P> {
+    fn clone(&self) -> Self {
+        *self
     }
 }
+impl<S, P> Copy for SourceLabel<S, P> {}
+impl<S, P> PartialEq for SourceLabel<S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<S, P> Eq for SourceLabel<S, P> {}
+
+/// A handle to a specific test [Label] from the MNIST dataset
+pub type TestLabel = SourceLabel<Mnist, Test>;
+impl TestLabel {
+    /// The number of test labels in MNIST. equal to [TestImage::COUNT]
+    pub const COUNT: usize = TestImage::COUNT;
+}
+
+/// A handle to a specific training [Label] from the MNIST dataset
+pub type TrainingLabel = SourceLabel<Mnist, Training>;
+impl TrainingLabel {
+    /// The number of training labels in MNIST. equal to [TrainingImage::COUNT]
+    pub const COUNT: usize = TrainingImage::COUNT;
+}
@@ -1,39 +1,104 @@
+pub mod augmentation;
+pub mod corruption;
+pub mod emnist;
+pub mod error;
+pub mod fashion;
+pub mod files;
+pub mod idx;
 pub mod image;
+pub mod kmnist;
 pub mod label;
+pub mod qmnist;
+pub mod sampling;
+pub mod subset;
+pub mod validation;
 pub mod visualization;
 
 #[cfg(feature = "burn")]
 pub mod burn_interop;
 
-pub use crate::{image::*, label::*};
+#[cfg(feature = "verify")]
+pub mod verify;
+
+#[cfg(feature = "ndarray")]
+pub mod batch;
+
+#[cfg(test)]
+mod test_util;
+
+pub use crate::{
+    error::MnistError,
+    files::{Files, Mnist, MnistFiles, Source, Split, Test, Training},
+    image::*,
+    label::*,
+};
+
+use crate::subset::{Epochs, Subset};
+
+use core::marker::PhantomData;
 
 #[cfg(feature = "burn")]
 use burn::prelude::*;
 
 #[cfg(feature = "ndarray")]
-use ndarray::{Array, Array2};
+use ndarray::{Array, Array1, Array2, ArrayView1};
 
 pub trait DataSet {
     type Image: Image;
     type Label: Label;
-    const COUNT: usize = Self::Image::COUNT;
+    fn count() -> usize {
+        Self::Image::count()
+    }
+    /// The image and label at `index`. Returns [None] if `index >= Self::count()`
+    fn get(index: usize) -> Option<(Self::Image, Self::Label)> {
+        Self::Image::from_index(index).zip(Self::Label::from_index(index))
+    }
     fn images() -> impl Iterator<Item = Self::Image> {
-        Self::Image::all()
+        Self::all().map(|(image, _)| image)
     }
     fn labels() -> impl Iterator<Item = Self::Label> {
-        Self::Label::all()
+        Self::all().map(|(_, label)| label)
     }
     fn images_normalized() -> impl Iterator<Item = [f32; IMAGE_SIZE]> {
         Self::images().map(|image| normalize_bytes(image.as_bytes()))
     }
-    fn labels_one_hot_encoded() -> impl Iterator<Item = [f32; DigitClass::COUNT]> {
-        Self::labels().map(|label| label.digit_class().one_hot_encode())
+    /// yields `<Self::Label as Label>::Class::COUNT` elements for each label
+    fn labels_one_hot_encoded() -> impl Iterator<Item = Vec<f32>> {
+        Self::labels().map(|label| label.class().one_hot_encode())
+    }
+    /// [DataSet::labels_one_hot_encoded] as fixed size arrays for data sets of digits
+    fn digit_labels_one_hot_encoded() -> impl Iterator<Item = [f32; DigitClass::COUNT]>
+    where
+        Self::Label: Label<Class = DigitClass>,
+    {
+        Self::labels().map(|label| label.class().one_hot_encode())
     }
     fn all() -> impl Iterator<Item = (Self::Image, Self::Label)> {
-        Self::images().zip(Self::labels())
+        (0..Self::count()).filter_map(Self::get)
+    }
+    /// Every example in an order that only depends on `seed`, see [Subset::shuffle]
+    fn shuffled(seed: u64) -> Subset<Self>
+    where
+        Self: Sized,
+    {
+        Subset::all().shuffled(seed)
+    }
+    /// Every example in a different order for every epoch, see [Subset::epochs]
+    fn epochs(seed: u64) -> Epochs<Self>
+    where
+        Self: Sized,
+    {
+        Subset::all().epochs(seed)
+    }
+    /// The examples whose class matches `predicate`, see [Subset::where_class]
+    fn where_class(predicate: impl FnMut(<Self::Label as Label>::Class) -> bool) -> Subset<Self>
+    where
+        Self: Sized,
+    {
+        Subset::where_class(predicate)
     }
 
-    /// Shape = `(Self::Image::COUNT, IMAGE_SIZE, 1)`
+    /// Shape = `(Self::count(), IMAGE_SIZE, 1)`
     #[cfg(feature = "ndarray")]
     fn input_column_vectors() -> impl Iterator<Item = Array2<f32>> {
         Self::images_normalized().map(|image| {
@@ -43,19 +108,62 @@ pub trait DataSet {
             )
         })
     }
-    /// Shape = `(Self::Label::COUNT, DigitClass::COUNT, 1)`
+    /// Shape = `(Self::count(), <Self::Label as Label>::Class::COUNT, 1)`
     #[cfg(feature = "ndarray")]
     fn output_column_vectors() -> impl Iterator<Item = Array2<f32>> {
         Self::labels_one_hot_encoded().map(|label| {
             Array::from_shape_fn(
-                (DigitClass::COUNT, 1), //
+                (<Self::Label as Label>::Class::COUNT, 1), //
                 |(i, _)| label[i],
             )
         })
     }
 
+    /// Shape = `(Self::count(), IMAGE_SIZE)`, one normalized image per row
+    #[cfg(feature = "ndarray")]
+    fn inputs() -> Array2<f32> {
+        let mut inputs = Array2::zeros((Self::count(), IMAGE_SIZE));
+        for (mut row, image) in inputs.rows_mut().into_iter().zip(Self::images_normalized()) {
+            row.assign(&ArrayView1::from(&image));
+        }
+        inputs
+    }
+    /// Shape = `(Self::count(), <Self::Label as Label>::Class::COUNT)`, one one hot encoded class per row
+    #[cfg(feature = "ndarray")]
+    fn outputs() -> Array2<f32> {
+        let mut outputs = Array2::zeros((Self::count(), <Self::Label as Label>::Class::COUNT));
+        for (row, label) in Self::labels().enumerate() {
+            outputs[[row, label.class().index()]] = 1.0;
+        }
+        outputs
+    }
+    /// Shape = `(Self::count(), IMAGE_SIZE)`, the bytes of one image per row
+    #[cfg(feature = "ndarray")]
+    fn inputs_u8() -> Array2<u8> {
+        let bytes = Self::images()
+            .flat_map(|image| image.as_bytes())
+            .copied()
+            .collect();
+        Array2::from_shape_vec((Self::count(), IMAGE_SIZE), bytes)
+            .expect("every image has IMAGE_SIZE bytes")
+    }
+    /// Shape = `(Self::count(),)`, the [ClassSet::index] of every label
+    #[cfg(feature = "ndarray")]
+    fn class_indices() -> Array1<usize> {
+        Self::labels().map(|label| label.class().index()).collect()
+    }
+
+    /// see [Batches](batch::Batches)
     #[cfg(feature = "ndarray")]
-    /// yields: [Array2D] of Shape = `(Self::Label::COUNT, DigitClass::COUNT, 1)`
+    fn batches(batch_size: usize) -> batch::Batches<Self>
+    where
+        Self: Sized,
+    {
+        Subset::all().batches(batch_size)
+    }
+
+    #[cfg(feature = "ndarray")]
+    /// yields: [Array2D] of Shape = `(Self::count(), <Self::Label as Label>::Class::COUNT, 1)`
     fn input_output_column_vectors() -> impl Iterator<Item = (Array2<f32>, Array2<f32>)> {
         Self::input_column_vectors().zip(Self::output_column_vectors())
     }
@@ -68,8 +176,8 @@ pub trait DataSet {
             .zip(Self::labels())
             .map(|(image, label)| {
                 (
-                    image.to_array(),
-                    [(label.digit_class() as i64).elem::<B::IntElem>()],
+                    TensorData::new(image.to_array().to_vec(), [IMAGE_HEIGHT, IMAGE_WIDTH]),
+                    [(label.class().index() as i64).elem::<B::IntElem>()],
                 )
             })
             .map(|(image_data, label_data)| {
@@ -80,52 +188,50 @@ pub trait DataSet {
             })
     }
 }
-pub enum TrainingData {}
-impl DataSet for TrainingData {
-    type Image = TrainingImage;
-    type Label = TrainingLabel;
+/// The [Split] `P` of the [Source] `S`
+pub struct SourceData<S, P>(PhantomData<fn() -> (S, P)>);
+impl<S: Source, P: Split> DataSet for SourceData<S, P> {
+    type Image = SourceImage<S, P>;
+    type Label = SourceLabel<S, P>;
 }
 
-pub enum TestData {}
-impl DataSet for TestData {
-    type Image = TestImage;
-    type Label = TestLabel;
-}
+pub type TrainingData = SourceData<Mnist, Training>;
+pub type TestData = SourceData<Mnist, Test>;
+
+/// The first 5,000 examples of [TestData]. They are taken from the original NIST training set and are cleaner and easier
+pub type EasyTestData = validation::TrainingPart<TestData, validation::Count<5_000>>;
+/// The last 5,000 examples of [TestData]. They are taken from the original NIST test set and are harder
+pub type HardTestData = validation::ValidationPart<TestData, validation::Count<5_000>>;
 
 #[cfg(test)]
+#[allow(clippy::manual_range_contains)]
 mod test {
     use crate::{
-        DataSet, IMAGE_HEIGHT, IMAGE_MAGIC_NUMBER, IMAGE_WIDTH, Image, LABEL_MAGIC_NUMBER, Label,
-        TestData, TestImage, TestLabel, TrainingData, TrainingImage, TrainingLabel,
+        ClassSet, DataSet, DigitClass, EasyTestData, HardTestData, IMAGE_HEIGHT,
+        IMAGE_MAGIC_NUMBER, IMAGE_WIDTH, Image, LABEL_MAGIC_NUMBER, Label, TestData, TestImage,
+        TestLabel, TrainingData, TrainingImage, TrainingLabel, image::u32_from_big_endian_bytes,
     };
     #[cfg(feature = "ndarray")]
-    use crate::{DigitClass, IMAGE_SIZE};
+    use crate::{IMAGE_OFFSET, IMAGE_SIZE};
     #[cfg(feature = "ndarray")]
     use ndarray::Axis;
 
-    const fn u32_from_big_endian_bytes(bytes: &[u8]) -> u32 {
-        (bytes[0] as u32) << 24
-            | (bytes[1] as u32) << 16
-            | (bytes[2] as u32) << 8
-            | (bytes[3] as u32)
-    }
-
     #[test]
     fn test_image_metadata() {
         assert_eq!(
-            u32_from_big_endian_bytes(&TestImage::RAW_DATA[0..4]),
+            u32_from_big_endian_bytes(&TestImage::raw_data()[0..4]),
             IMAGE_MAGIC_NUMBER
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TestImage::RAW_DATA[4..8]),
+            u32_from_big_endian_bytes(&TestImage::raw_data()[4..8]),
             TestImage::COUNT as u32
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TestImage::RAW_DATA[8..12]),
+            u32_from_big_endian_bytes(&TestImage::raw_data()[8..12]),
             IMAGE_HEIGHT as u32
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TestImage::RAW_DATA[12..16]),
+            u32_from_big_endian_bytes(&TestImage::raw_data()[12..16]),
             IMAGE_WIDTH as u32
         );
     }
@@ -133,11 +239,11 @@ mod test {
     #[test]
     fn test_label_metadata() {
         assert_eq!(
-            u32_from_big_endian_bytes(&TestLabel::RAW_DATA[0..4]),
+            u32_from_big_endian_bytes(&TestLabel::raw_data()[0..4]),
             LABEL_MAGIC_NUMBER
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TestLabel::RAW_DATA[4..8]),
+            u32_from_big_endian_bytes(&TestLabel::raw_data()[4..8]),
             TestImage::COUNT as u32
         );
     }
@@ -145,19 +251,19 @@ mod test {
     #[test]
     fn training_image_metadata() {
         assert_eq!(
-            u32_from_big_endian_bytes(&TrainingImage::RAW_DATA[0..4]),
+            u32_from_big_endian_bytes(&TrainingImage::raw_data()[0..4]),
             IMAGE_MAGIC_NUMBER
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TrainingImage::RAW_DATA[4..8]),
+            u32_from_big_endian_bytes(&TrainingImage::raw_data()[4..8]),
             TrainingImage::COUNT as u32
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TrainingImage::RAW_DATA[8..12]),
+            u32_from_big_endian_bytes(&TrainingImage::raw_data()[8..12]),
             IMAGE_HEIGHT as u32
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TrainingImage::RAW_DATA[12..16]),
+            u32_from_big_endian_bytes(&TrainingImage::raw_data()[12..16]),
             IMAGE_WIDTH as u32
         );
     }
@@ -165,15 +271,30 @@ mod test {
     #[test]
     fn training_label_metadata() {
         assert_eq!(
-            u32_from_big_endian_bytes(&TrainingLabel::RAW_DATA[0..4]),
+            u32_from_big_endian_bytes(&TrainingLabel::raw_data()[0..4]),
             LABEL_MAGIC_NUMBER
         );
         assert_eq!(
-            u32_from_big_endian_bytes(&TrainingLabel::RAW_DATA[4..8]),
+            u32_from_big_endian_bytes(&TrainingLabel::raw_data()[4..8]),
             TrainingImage::COUNT as u32
         );
     }
 
+    #[test]
+    fn digit_class_set() {
+        for digit_class in <DigitClass as ClassSet>::all() {
+            let encoded = ClassSet::one_hot_encode(digit_class);
+            assert_eq!(encoded, digit_class.one_hot_encode());
+            assert_eq!(DigitClass::one_hot_decode(&encoded), Some(digit_class));
+            assert_eq!(
+                <DigitClass as ClassSet>::from_byte(digit_class.to_byte()),
+                Some(digit_class)
+            );
+        }
+        assert_eq!(<DigitClass as ClassSet>::all().count(), DigitClass::COUNT);
+        assert_eq!(DigitClass::one_hot_decode(&[0.5; 3]), None);
+    }
+
     #[test]
     fn test_counts() {
         assert_eq!(TestData::images_normalized().count(), TestImage::COUNT);
@@ -186,6 +307,21 @@ mod test {
             TrainingData::labels_one_hot_encoded().count(),
             TrainingLabel::COUNT
         );
+        assert!(
+            TestData::digit_labels_one_hot_encoded()
+                .map(Vec::from)
+                .eq(TestData::labels_one_hot_encoded())
+        );
+    }
+
+    #[test]
+    fn test_halves() {
+        assert_eq!(EasyTestData::count(), 5_000);
+        assert_eq!(HardTestData::count(), 5_000);
+        assert_eq!(EasyTestData::get(0), TestData::get(0));
+        assert_eq!(HardTestData::get(0), TestData::get(5_000));
+        assert_eq!(HardTestData::get(4_999), TestData::get(9_999));
+        assert!(EasyTestData::get(5_000).is_none());
     }
 
     #[cfg(feature = "ndarray")]
@@ -214,4 +350,22 @@ mod test {
             }
         }
     }
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn views_borrow_raw_data() {
+        let images = TestImage::view_all();
+        assert_eq!(images.dim(), (TestImage::COUNT, IMAGE_HEIGHT, IMAGE_WIDTH));
+        assert_eq!(
+            images.as_ptr(),
+            TestImage::raw_data()[IMAGE_OFFSET..].as_ptr()
+        );
+
+        let image = TestImage::from_index(1234).unwrap();
+        assert_eq!(image.view(), images.index_axis(Axis(0), 1234));
+        assert_eq!(image.view().as_ptr(), image.as_bytes().as_ptr());
+        assert_eq!(
+            TestImage::view_all_flat().row(1234).as_slice().unwrap(),
+            image.as_bytes()
+        );
+    }
 }
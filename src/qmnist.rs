@@ -0,0 +1,291 @@
+//! [QMNIST](https://github.com/facebookresearch/qmnist): a reconstruction of MNIST from NIST Special Database 19
+//! with the full test set of 60,000 images.
+//!
+//! The labels files are `idx2-int` files with 8 columns per image instead of `idx1-ubyte` files.
+//! They are decoded into a labels file of [DigitClass]es followed by the original rows,
+//! which [QmnistTrainingLabel::provenance] and [QmnistTestLabel::provenance] read.
+
+use crate::{
+    ClassSet, DigitClass, Files, LABEL_OFFSET, Label, MnistError, SourceData, SourceImage,
+    SourceLabel, Test, Training,
+    files::{FileBytes, FileKind, Slots, Source, Split},
+    idx::{self, Idx},
+    image::u32_from_big_endian_bytes,
+    validate_images,
+    validation::{KeepFirst, TrainingPart, ValidationPart},
+};
+
+use std::borrow::Cow;
+
+/// Each QMNIST labels file starts with `3074`: a rank 2 file of `i32`s
+pub const QMNIST_LABEL_MAGIC_NUMBER: u32 = 0x0C02;
+/// The number of `i32` columns per image in a QMNIST labels file
+pub const QMNIST_LABEL_COLUMNS: usize = 8;
+/// The first 10,000 test images reconstruct the MNIST test set.
+/// The other 50,000 are the ones that were lost when MNIST was made
+pub const MNIST_TEST_COUNT: usize = 10_000;
+
+const ROW_SIZE: usize = QMNIST_LABEL_COLUMNS * size_of::<i32>();
+
+/// The QMNIST dataset.
+/// Read from `dataset/qmnist/` or `$QMNIST_DATASET_DIR` by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qmnist {}
+impl Source for Qmnist {
+    type Class = DigitClass;
+    const NAME: &'static str = "QMNIST";
+    const DIRECTORY_VARIABLE: &'static str = "QMNIST_DATASET_DIR";
+    const DIRECTORY: &'static str = "dataset/qmnist";
+
+    /// The names used by the original distribution
+    fn file_names(kind: FileKind) -> &'static [&'static str] {
+        match kind {
+            FileKind::TrainingImages => &["qmnist-train-images-idx3-ubyte"],
+            FileKind::TrainingLabels => &["qmnist-train-labels-idx2-int"],
+            FileKind::TestImages => &["qmnist-test-images-idx3-ubyte"],
+            FileKind::TestLabels => &["qmnist-test-labels-idx2-int"],
+        }
+    }
+
+    fn slots() -> &'static Slots<Self> {
+        static SLOTS: Slots<Qmnist> = Slots::new();
+        &SLOTS
+    }
+
+    fn validate(kind: FileKind, bytes: &[u8]) -> Result<usize, MnistError> {
+        match kind {
+            FileKind::TrainingImages | FileKind::TestImages => validate_images(bytes),
+            FileKind::TrainingLabels | FileKind::TestLabels => validate_qmnist_labels(bytes),
+        }
+    }
+
+    fn decode(kind: FileKind, bytes: FileBytes) -> FileBytes {
+        match kind {
+            FileKind::TrainingImages | FileKind::TestImages => bytes,
+            FileKind::TrainingLabels | FileKind::TestLabels => {
+                let idx = Idx::parse(&bytes).expect("labels are validated before they are decoded");
+                let rows = idx.data();
+                let classes = rows
+                    .chunks_exact(ROW_SIZE)
+                    .map(|row| u32_from_big_endian_bytes(row) as u8)
+                    .collect::<Vec<_>>();
+                let mut decoded = Vec::with_capacity(LABEL_OFFSET + classes.len() + rows.len());
+                idx::write(&mut decoded, &[classes.len()], &classes)
+                    .expect("writing to a Vec can't fail");
+                decoded.extend_from_slice(rows);
+                decoded.into_boxed_slice().into()
+            }
+        }
+    }
+
+    fn decode_copies(kind: FileKind) -> bool {
+        matches!(kind, FileKind::TrainingLabels | FileKind::TestLabels)
+    }
+
+    /// Labels without rows, e.g. from [Files::from_examples], get rows of zeros
+    fn encode(kind: FileKind, bytes: &[u8]) -> Cow<'_, [u8]> {
+        match kind {
+            FileKind::TrainingImages | FileKind::TestImages => Cow::Borrowed(bytes),
+            FileKind::TrainingLabels | FileKind::TestLabels => {
+                let count = u32_from_big_endian_bytes(&bytes[4..8]) as usize;
+                let (classes, rows) = bytes[LABEL_OFFSET..].split_at(count);
+                let values = if rows.len() == count * ROW_SIZE {
+                    rows.as_chunks::<4>()
+                        .0
+                        .iter()
+                        .map(|value| i32::from_be_bytes(*value))
+                        .collect::<Vec<_>>()
+                } else {
+                    classes
+                        .iter()
+                        .flat_map(|&class| {
+                            let mut row = [0; QMNIST_LABEL_COLUMNS];
+                            row[0] = class as i32;
+                            row
+                        })
+                        .collect()
+                };
+                let mut encoded = Vec::new();
+                idx::write(&mut encoded, &[count, QMNIST_LABEL_COLUMNS], &values)
+                    .expect("writing to a Vec can't fail");
+                Cow::Owned(encoded)
+            }
+        }
+    }
+}
+
+/// Checks that `bytes` is a complete QMNIST labels file where every class is a [DigitClass].
+/// Returns the number of labels
+pub fn validate_qmnist_labels(bytes: &[u8]) -> Result<usize, MnistError> {
+    let idx = Idx::parse(bytes)?;
+    idx.expect_magic_number(QMNIST_LABEL_MAGIC_NUMBER)?;
+    let count = match *idx.dimensions() {
+        [count, QMNIST_LABEL_COLUMNS] => count,
+        ref actual => {
+            return Err(MnistError::WrongDimensions {
+                expected: vec![actual[0], QMNIST_LABEL_COLUMNS],
+                actual: actual.to_vec(),
+            });
+        }
+    };
+    let rows = idx
+        .view::<i32>()
+        .expect("the magic number says these are i32s");
+    for index in 0..count {
+        let class = rows.get(&[index, 0]).expect("index < count");
+        if usize::try_from(class)
+            .ok()
+            .and_then(DigitClass::from_index)
+            .is_none()
+        {
+            return Err(MnistError::LabelOutOfRange {
+                index,
+                value: class,
+            });
+        }
+    }
+    Ok(count)
+}
+
+/// Where an image of QMNIST comes from in NIST Special Database 19
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Provenance {
+    /// The NIST HSF series the writer belongs to: `0`, `1`, `2` or `4`
+    pub hsf_series: u32,
+    /// The NIST writer ID
+    pub writer_id: u32,
+    /// The index of this digit among the digits of its writer
+    pub writer_digit_index: u32,
+    /// The NIST class code
+    pub nist_class_code: u32,
+    /// The index of this digit in NIST Special Database 19
+    pub nist_index: u32,
+    /// Whether this image duplicates another one
+    pub duplicate: bool,
+}
+
+impl<P: Split> SourceLabel<Qmnist, P> {
+    /// Returns [None] if the labels file has no rows, e.g. if it was built with [Files::from_examples]
+    pub fn provenance(&self) -> Option<Provenance> {
+        let bytes = Self::raw_data();
+        let rows = bytes.get(LABEL_OFFSET + Self::count()..)?;
+        let row = rows.as_chunks::<ROW_SIZE>().0.get(self.index())?;
+        let column = |column: usize| u32_from_big_endian_bytes(&row[column * 4..]);
+        Some(Provenance {
+            hsf_series: column(1),
+            writer_id: column(2),
+            writer_digit_index: column(3),
+            nist_class_code: column(4),
+            nist_index: column(5),
+            duplicate: column(6) != 0,
+        })
+    }
+}
+
+/// The contents of the four QMNIST files, read at runtime
+pub type QmnistFiles = Files<Qmnist>;
+
+/// A handle to a specific training [Image](crate::Image) from the QMNIST dataset
+pub type QmnistTrainingImage = SourceImage<Qmnist, Training>;
+/// A handle to a specific training [Label](crate::Label) from the QMNIST dataset
+pub type QmnistTrainingLabel = SourceLabel<Qmnist, Training>;
+/// A handle to a specific test [Image](crate::Image) from the QMNIST dataset
+pub type QmnistTestImage = SourceImage<Qmnist, Test>;
+/// A handle to a specific test [Label](crate::Label) from the QMNIST dataset
+pub type QmnistTestLabel = SourceLabel<Qmnist, Test>;
+
+pub type QmnistTrainingData = SourceData<Qmnist, Training>;
+pub type QmnistTestData = SourceData<Qmnist, Test>;
+
+/// The first [MNIST_TEST_COUNT] examples of [QmnistTestData], which reconstruct the MNIST test set
+pub type QmnistMnistTestData = TrainingPart<QmnistTestData, KeepFirst<MNIST_TEST_COUNT>>;
+/// The examples of [QmnistTestData] after the first [MNIST_TEST_COUNT], which were lost when MNIST was made
+pub type QmnistLostTestData = ValidationPart<QmnistTestData, KeepFirst<MNIST_TEST_COUNT>>;
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DataSet, DigitClass, IMAGE_SIZE, Label, MnistError, idx,
+        qmnist::{
+            Provenance, QMNIST_LABEL_COLUMNS, QmnistFiles, QmnistLostTestData, QmnistMnistTestData,
+            QmnistTestData, QmnistTrainingLabel,
+        },
+        test_util::temp_directory,
+    };
+
+    #[test]
+    fn labels_keep_their_provenance() {
+        let mut images = Vec::new();
+        idx::write_images(&mut images, [[0; IMAGE_SIZE]; 2]).unwrap();
+        let mut labels = Vec::new();
+        let rows: [i32; 2 * QMNIST_LABEL_COLUMNS] =
+            [3, 0, 2100, 7, 51, 8, 0, 0, 9, 4, 3900, 12, 57, 402, 1, 0];
+        idx::write(&mut labels, &[2, QMNIST_LABEL_COLUMNS], &rows).unwrap();
+
+        let data_offset = idx::Header::parse(&labels).unwrap().data_offset();
+        let mut bad_labels = labels.clone();
+        bad_labels[data_offset + 3] = 10;
+        assert!(matches!(
+            QmnistFiles::from_bytes(images.clone(), bad_labels, images.clone(), labels.clone()),
+            Err(MnistError::LabelOutOfRange {
+                index: 0,
+                value: 10
+            })
+        ));
+        let mut negative_labels = labels.clone();
+        negative_labels[data_offset..data_offset + 4].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(matches!(
+            QmnistFiles::from_bytes(
+                images.clone(),
+                negative_labels,
+                images.clone(),
+                labels.clone()
+            ),
+            Err(MnistError::LabelOutOfRange {
+                index: 0,
+                value: -1
+            })
+        ));
+
+        let files = QmnistFiles::from_bytes(images.clone(), labels.clone(), images, labels.clone())
+            .unwrap();
+        let directory = temp_directory("qmnist_provenance");
+        files.write(&directory).unwrap();
+        assert_eq!(
+            std::fs::read(directory.join("qmnist-test-labels-idx2-int")).unwrap(),
+            labels
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        files.install().unwrap();
+        let label = QmnistTrainingLabel::from_index(1).unwrap();
+        assert_eq!(label.digit_class(), DigitClass::Nine);
+        assert_eq!(
+            label.provenance(),
+            Some(Provenance {
+                hsf_series: 4,
+                writer_id: 3900,
+                writer_digit_index: 12,
+                nist_class_code: 57,
+                nist_index: 402,
+                duplicate: true,
+            })
+        );
+
+        assert_eq!(QmnistMnistTestData::count(), 2);
+        assert_eq!(QmnistMnistTestData::get(1), QmnistTestData::get(1));
+        assert_eq!(QmnistLostTestData::count(), 0);
+    }
+
+    #[test]
+    fn examples_get_empty_rows() {
+        let files = QmnistFiles::from_examples(
+            [([0; IMAGE_SIZE], DigitClass::Five)],
+            [([0; IMAGE_SIZE], DigitClass::Six)],
+        );
+        let directory = temp_directory("qmnist_examples");
+        files.write(&directory).unwrap();
+        assert_eq!(QmnistFiles::open(&directory).unwrap().test_labels()[8], 6);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
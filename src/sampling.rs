@@ -0,0 +1,123 @@
+//! Reproducible samples of a [DataSet] for low-data experiments and imbalanced classes.
+//!
+//! [per_class] and [with_quotas] choose examples without replacement, [balanced] and [weighted] with replacement.
+//! Every sampler returns a [Subset] of handles and only depends on its arguments and `seed`.
+
+use crate::{
+    ClassSet, DataSet, Label,
+    subset::{Subset, indices_by_class, seeded_rng},
+};
+
+use rand::{
+    Rng,
+    distr::{Distribution, weighted::WeightedIndex},
+    seq::SliceRandom,
+};
+
+/// `n` examples of every class chosen at random, or every example of a class with fewer than `n`.
+/// The subset is in the order of `D`
+pub fn per_class<D: DataSet>(n: usize, seed: u64) -> Subset<D> {
+    with_quotas(|_| n, seed)
+}
+
+/// `quota(class)` examples of every class chosen at random, or every example of a class with fewer.
+/// The subset is in the order of `D`
+pub fn with_quotas<D: DataSet>(
+    mut quota: impl FnMut(<D::Label as Label>::Class) -> usize,
+    seed: u64,
+) -> Subset<D> {
+    let mut rng = seeded_rng(seed, 0);
+    let mut indices = Vec::new();
+    for (class, mut examples) in classes::<D>() {
+        let (chosen, _) = examples.partial_shuffle(&mut rng, quota(class));
+        indices.extend_from_slice(chosen);
+    }
+    indices.sort_unstable();
+    Subset::new_unchecked(indices)
+}
+
+/// `count` examples drawn with replacement so that every class is equally likely, oversampling rare classes.
+/// Classes without examples are never drawn
+pub fn balanced<D: DataSet>(count: usize, seed: u64) -> Subset<D> {
+    weighted(count, |_| 1.0, seed)
+}
+
+/// `count` examples drawn with replacement. Each draw picks a class with probability proportional to `weight(class)`,
+/// then one of its examples uniformly. Classes without examples are never drawn
+///
+/// # Panics
+/// if a weight is negative or not finite, or if no class with examples has a positive weight while `count > 0`
+pub fn weighted<D: DataSet>(
+    count: usize,
+    mut weight: impl FnMut(<D::Label as Label>::Class) -> f64,
+    seed: u64,
+) -> Subset<D> {
+    if count == 0 {
+        return Subset::new_unchecked(Vec::new());
+    }
+    let classes = classes::<D>()
+        .filter(|(_, examples)| !examples.is_empty())
+        .collect::<Vec<_>>();
+    let weights = classes.iter().map(|&(class, _)| weight(class));
+    let distribution =
+        WeightedIndex::new(weights).expect("weights must be finite, non-negative and not all zero");
+    let mut rng = seeded_rng(seed, 0);
+    let indices = (0..count)
+        .map(|_| {
+            let (_, examples) = &classes[distribution.sample(&mut rng)];
+            examples[rng.random_range(0..examples.len())]
+        })
+        .collect();
+    Subset::new_unchecked(indices)
+}
+
+fn classes<D: DataSet>() -> impl Iterator<Item = (<D::Label as Label>::Class, Vec<usize>)> {
+    indices_by_class::<D>()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(class, examples)| Some((ClassSet::from_index(class)?, examples)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassSet, DigitClass, SourceData,
+        files::Training,
+        sampling::{balanced, per_class, weighted, with_quotas},
+        test_util::{class_counts, in_memory_source},
+    };
+
+    in_memory_source!(
+        /// `c + 1` examples of each class `c`
+        Skewed,
+        |class| class.index() + 1
+    );
+    type Data = SourceData<Skewed, Training>;
+
+    #[test]
+    fn quotas_cap_at_class_size() {
+        Skewed::install();
+        let few_shot = per_class::<Data>(3, 7);
+        assert_eq!(class_counts(&few_shot), [1, 2, 3, 3, 3, 3, 3, 3, 3, 3]);
+        assert!(few_shot.indices().is_sorted());
+        assert_eq!(per_class::<Data>(3, 7), few_shot);
+        assert_ne!(per_class::<Data>(3, 8), few_shot);
+
+        let quotas = with_quotas::<Data>(|class| 9 - class.index(), 7);
+        assert_eq!(class_counts(&quotas), [1, 2, 3, 4, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn balanced_sampling_oversamples_rare_classes() {
+        Skewed::install();
+        let sample = balanced::<Data>(10_000, 7);
+        assert_eq!(sample.len(), 10_000);
+        assert_eq!(balanced::<Data>(10_000, 7), sample);
+        for count in class_counts(&sample) {
+            assert!((800..1200).contains(&count), "{count}");
+        }
+
+        let only_zeros = weighted::<Data>(50, |class| (class == DigitClass::Zero).into(), 7);
+        assert_eq!(only_zeros.indices(), [0; 50]);
+    }
+}
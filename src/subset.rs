@@ -0,0 +1,238 @@
+//! Views of a [DataSet] chosen at runtime by a list of indices or by class
+
+use crate::{ClassSet, DataSet, Label};
+
+use core::{fmt, marker::PhantomData};
+use rand::{SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+
+/// The examples of `D` at a list of indices. Index `i` of the subset is index `indices()[i]` of `D`
+pub struct Subset<D> {
+    indices: Vec<usize>,
+    data_set: PhantomData<fn() -> D>,
+}
+impl<D: DataSet> Subset<D> {
+    /// Returns [None] if any of `indices` is out of bounds for `D`
+    pub fn from_indices(indices: impl Into<Vec<usize>>) -> Option<Self> {
+        let indices = indices.into();
+        let count = D::count();
+        indices
+            .iter()
+            .all(|&index| index < count)
+            .then(|| Self::new_unchecked(indices))
+    }
+    /// Every example of `D`
+    pub fn all() -> Self {
+        Self::new_unchecked((0..D::count()).collect())
+    }
+    /// The examples of `D` whose class matches `predicate`, in the order of `D`
+    pub fn where_class(predicate: impl FnMut(<D::Label as Label>::Class) -> bool) -> Self {
+        Self::all().filter_classes(predicate)
+    }
+    pub(crate) fn new_unchecked(indices: Vec<usize>) -> Self {
+        Self {
+            indices,
+            data_set: PhantomData,
+        }
+    }
+
+    /// The indices into `D`
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// The image and label at `index` of this subset
+    pub fn get(&self, index: usize) -> Option<(D::Image, D::Label)> {
+        D::get(*self.indices.get(index)?)
+    }
+    pub fn all_examples(&self) -> impl ExactSizeIterator<Item = (D::Image, D::Label)> + '_ {
+        self.indices
+            .iter()
+            .map(|&index| D::get(index).expect("subsets only contain valid indices"))
+    }
+    pub fn images(&self) -> impl ExactSizeIterator<Item = D::Image> + '_ {
+        self.all_examples().map(|(image, _)| image)
+    }
+    pub fn labels(&self) -> impl ExactSizeIterator<Item = D::Label> + '_ {
+        self.all_examples().map(|(_, label)| label)
+    }
+
+    /// Keeps the examples whose class matches `predicate`. Index `i` of the result is the `i`th kept example
+    pub fn filter_classes(
+        mut self,
+        mut predicate: impl FnMut(<D::Label as Label>::Class) -> bool,
+    ) -> Self {
+        self.indices
+            .retain(|&index| D::get(index).is_some_and(|(_, label)| predicate(label.class())));
+        self
+    }
+    /// The examples of this subset at `indices`. Returns [None] if any of `indices` is out of bounds for this subset
+    pub fn select(&self, indices: &[usize]) -> Option<Self> {
+        indices
+            .iter()
+            .map(|&index| self.indices.get(index).copied())
+            .collect::<Option<_>>()
+            .map(Self::new_unchecked)
+    }
+
+    /// Shuffles the indices, not the image bytes. The new order only depends on `seed` and the current order
+    pub fn shuffle(&mut self, seed: u64) {
+        self.indices.shuffle(&mut seeded_rng(seed, 0));
+    }
+    /// see [Subset::shuffle]
+    pub fn shuffled(mut self, seed: u64) -> Self {
+        self.shuffle(seed);
+        self
+    }
+    /// see [Batches](crate::batch::Batches)
+    #[cfg(feature = "ndarray")]
+    pub fn batches(self, batch_size: usize) -> crate::batch::Batches<D> {
+        crate::batch::Batches::new(self, batch_size)
+    }
+    /// An endless iterator of this subset shuffled differently for every epoch
+    pub fn epochs(self, seed: u64) -> Epochs<D> {
+        Epochs {
+            subset: self,
+            seed,
+            epoch: 0,
+        }
+    }
+}
+
+/// Yields a differently shuffled copy of a [Subset] for every epoch. see [Subset::epochs]
+pub struct Epochs<D> {
+    subset: Subset<D>,
+    seed: u64,
+    epoch: u64,
+}
+impl<D> Epochs<D> {
+    /// The order of epoch `n` only depends on the seed and `n`
+    pub fn epoch(&self, n: u64) -> Subset<D> {
+        let mut subset = self.subset.clone();
+        subset.indices.shuffle(&mut seeded_rng(self.seed, n));
+        subset
+    }
+}
+impl<D> Iterator for Epochs<D> {
+    type Item = Subset<D>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let subset = self.epoch(self.epoch);
+        self.epoch += 1;
+        Some(subset)
+    }
+}
+impl<D> fmt::Debug for Epochs<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Epochs")
+            .field("subset", &self.subset)
+            .field("seed", &self.seed)
+            .field("epoch", &self.epoch)
+            .finish()
+    }
+}
+
+/// Independent random number generators for every `stream` of a `seed`
+pub(crate) fn seeded_rng(seed: u64, stream: u64) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(stream);
+    rng
+}
+impl<D> fmt::Debug for Subset<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subset")
+            .field("indices", &self.indices)
+            .finish()
+    }
+}
+impl<D> Clone for Subset<D> {
+    fn clone(&self) -> Self {
+        Self {
+            indices: self.indices.clone(),
+            data_set: PhantomData,
+        }
+    }
+}
+impl<D> PartialEq for Subset<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.indices == other.indices
+    }
+}
+impl<D> Eq for Subset<D> {}
+
+/// The indices of the examples of `D` grouped by the [ClassSet::index] of their class
+pub(crate) fn indices_by_class<D: DataSet>() -> Vec<Vec<usize>> {
+    let mut classes = vec![Vec::new(); <D::Label as Label>::Class::COUNT];
+    for index in 0..D::count() {
+        if let Some((_, label)) = D::get(index) {
+            classes[label.class().index()].push(index);
+        }
+    }
+    classes
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DataSet, DigitClass, Label, TestData, TestLabel, TrainingData, subset::Subset};
+
+    fn subset() -> Subset<TrainingData> {
+        Subset::new_unchecked((0..100).collect())
+    }
+
+    #[test]
+    fn shuffles_are_seeded_permutations() {
+        let shuffled = subset().shuffled(42);
+        assert_ne!(shuffled, subset());
+        assert_eq!(shuffled, subset().shuffled(42));
+        assert_ne!(shuffled, subset().shuffled(43));
+        let mut indices = shuffled.indices().to_vec();
+        indices.sort_unstable();
+        assert_eq!(indices, subset().indices());
+    }
+
+    #[test]
+    fn epochs_are_reproducible() {
+        let epochs = subset().epochs(42).take(3).collect::<Vec<_>>();
+        assert_ne!(epochs[0], epochs[1]);
+        assert_ne!(epochs[1], epochs[2]);
+        assert_eq!(subset().epochs(42).take(3).collect::<Vec<_>>(), epochs);
+        assert_eq!(subset().epochs(42).epoch(2), epochs[2]);
+    }
+
+    #[test]
+    fn class_filters_remap_densely() {
+        let is_three_or_eight = |class| matches!(class, DigitClass::Three | DigitClass::Eight);
+        let subset = Subset::<TestData>::where_class(is_three_or_eight);
+        let expected = TestLabel::all()
+            .filter(|label| is_three_or_eight(label.digit_class()))
+            .map(|label| label.index())
+            .collect::<Vec<_>>();
+        assert_eq!(subset.indices(), expected);
+        assert_eq!(subset.labels().len(), expected.len());
+        assert!(
+            subset
+                .labels()
+                .all(|label| is_three_or_eight(label.class()))
+        );
+        assert_eq!(subset.get(0).unwrap().1.index(), expected[0]);
+        assert!(subset.get(expected.len()).is_none());
+
+        let threes = subset
+            .clone()
+            .filter_classes(|class| class == DigitClass::Three);
+        assert!(threes.len() < subset.len());
+        assert_eq!(
+            TestData::where_class(|class| class == DigitClass::Three),
+            threes
+        );
+
+        let selected = subset.select(&[2, 0]).unwrap();
+        assert_eq!(selected.indices(), [expected[2], expected[0]]);
+        assert!(subset.select(&[expected.len()]).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers shared by the tests of several modules
+
+use crate::{ClassSet, DataSet, Label, subset::Subset};
+
+use std::path::PathBuf;
+
+/// A directory under [std::env::temp_dir] that is unique to `name` and to this process,
+/// so that concurrent `cargo test` runs don't clobber each other's files
+pub(crate) fn temp_directory(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mnist_dataset_{name}_{}", std::process::id()))
+}
+
+/// Declares the empty enum `$name` as a [Source](crate::Source) of [DigitClass](crate::DigitClass)es
+/// without files on disk. The first call to `$name::install()` installs `$count(class)` training and test
+/// examples of each class, in class order, whose pixels are all `class as u8`
+macro_rules! in_memory_source {
+    ($(#[$attribute:meta])* $name:ident, $count:expr) => {
+        $(#[$attribute])*
+        enum $name {}
+        impl $crate::Source for $name {
+            type Class = $crate::DigitClass;
+            const NAME: &'static str = stringify!($name);
+            const DIRECTORY_VARIABLE: &'static str = "";
+            const DIRECTORY: &'static str = "";
+            fn file_names(_: $crate::files::FileKind) -> &'static [&'static str] {
+                &[]
+            }
+            fn slots() -> &'static $crate::files::Slots<Self> {
+                static SLOTS: $crate::files::Slots<$name> = $crate::files::Slots::new();
+                &SLOTS
+            }
+        }
+        impl $name {
+            fn install() {
+                static INSTALL: std::sync::Once = std::sync::Once::new();
+                INSTALL.call_once(|| {
+                    let count: fn($crate::DigitClass) -> usize = $count;
+                    let examples = || {
+                        <$crate::DigitClass as $crate::ClassSet>::all().flat_map(|class| {
+                            let image = [class as u8; $crate::IMAGE_SIZE];
+                            std::iter::repeat_n((image, class), count(class))
+                        })
+                    };
+                    $crate::Files::<$name>::from_examples(examples(), examples())
+                        .install()
+                        .unwrap();
+                });
+            }
+        }
+    };
+}
+pub(crate) use in_memory_source;
+
+/// The number of examples of each class in `subset`, indexed by [ClassSet::index]
+pub(crate) fn class_counts<D: DataSet>(subset: &Subset<D>) -> Vec<usize> {
+    let mut counts = vec![0; <D::Label as Label>::Class::COUNT];
+    subset
+        .labels()
+        .for_each(|label| counts[label.class().index()] += 1);
+    counts
+}
@@ -0,0 +1,282 @@
+//! Deterministic train/validation splits of a [DataSet].
+//!
+//! The last [Holdout::validation_count] examples of `D` are the [ValidationPart], the rest are the [TrainingPart].
+//! Both are [DataSet]s themselves, e.g. `TrainingPart<TrainingData, Last10k>`.
+//!
+//! [stratified_split] and [k_fold] choose the examples at random from a seed instead and keep the
+//! proportion of each class the same in every part. They return [Subset]s.
+
+use crate::{
+    DataSet,
+    subset::{Subset, indices_by_class},
+};
+
+use core::{fmt, marker::PhantomData};
+use rand::{SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+
+/// How many examples of a [DataSet] are held out for validation
+pub trait Holdout: 'static {
+    /// At most `count`
+    fn validation_count(count: usize) -> usize;
+}
+
+/// Holds out the last `N` examples, or all of them if there are fewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count<const N: usize> {}
+impl<const N: usize> Holdout for Count<N> {
+    fn validation_count(count: usize) -> usize {
+        N.min(count)
+    }
+}
+
+/// Keeps the first `N` examples and holds out the rest, or holds out nothing if there are fewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepFirst<const N: usize> {}
+impl<const N: usize> Holdout for KeepFirst<N> {
+    fn validation_count(count: usize) -> usize {
+        count.saturating_sub(N)
+    }
+}
+
+/// Holds out `NUMERATOR / DENOMINATOR` of the examples, rounded down.
+/// Fails to compile unless `0 < DENOMINATOR` and `NUMERATOR <= DENOMINATOR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fraction<const NUMERATOR: usize, const DENOMINATOR: usize> {}
+impl<const NUMERATOR: usize, const DENOMINATOR: usize> Holdout
+    for Fraction<NUMERATOR, DENOMINATOR>
+{
+    fn validation_count(count: usize) -> usize {
+        const {
+            assert!(DENOMINATOR > 0, "the denominator must not be 0");
+            assert!(
+                NUMERATOR <= DENOMINATOR,
+                "can't hold out more than all of the examples"
+            );
+        }
+        // computed in u128 so that `count * NUMERATOR` can't overflow
+        (count as u128 * NUMERATOR as u128 / DENOMINATOR as u128) as usize
+    }
+}
+
+/// The conventional split of the MNIST training set: 50,000 for training and the last 10,000 for validation
+pub type Last10k = Count<10_000>;
+
+/// The examples of `D` that [Holdout] `H` doesn't hold out
+pub struct TrainingPart<D, H>(PhantomData<fn() -> (D, H)>);
+impl<D: DataSet, H: Holdout> DataSet for TrainingPart<D, H> {
+    type Image = D::Image;
+    type Label = D::Label;
+    fn count() -> usize {
+        D::count() - H::validation_count(D::count())
+    }
+    fn get(index: usize) -> Option<(Self::Image, Self::Label)> {
+        (index < Self::count()).then(|| D::get(index)).flatten()
+    }
+}
+
+/// The examples of `D` that [Holdout] `H` holds out
+pub struct ValidationPart<D, H>(PhantomData<fn() -> (D, H)>);
+impl<D: DataSet, H: Holdout> DataSet for ValidationPart<D, H> {
+    type Image = D::Image;
+    type Label = D::Label;
+    fn count() -> usize {
+        H::validation_count(D::count())
+    }
+    fn get(index: usize) -> Option<(Self::Image, Self::Label)> {
+        (index < Self::count())
+            .then(|| D::get(D::count() - Self::count() + index))
+            .flatten()
+    }
+}
+
+/// Splits `D` into `(training, validation)` subsets where each class holds out `validation_fraction`
+/// of its examples, rounded to the nearest example. The same `seed` always gives the same split
+pub fn stratified_split<D: DataSet>(validation_fraction: f64, seed: u64) -> (Subset<D>, Subset<D>) {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut training = Vec::new();
+    let mut validation = Vec::new();
+    for mut class in indices_by_class::<D>() {
+        class.shuffle(&mut rng);
+        let held_out =
+            ((class.len() as f64 * validation_fraction).round() as usize).min(class.len());
+        validation.extend_from_slice(&class[..held_out]);
+        training.extend_from_slice(&class[held_out..]);
+    }
+    training.sort_unstable();
+    validation.sort_unstable();
+    (
+        Subset::new_unchecked(training),
+        Subset::new_unchecked(validation),
+    )
+}
+
+/// Splits `D` into `k` folds for cross-validation. The examples of each class are spread evenly over the folds.
+/// The same `seed` always gives the same folds
+///
+/// # Panics
+/// if `k < 2`
+pub fn k_fold<D: DataSet>(k: usize, seed: u64) -> KFold<D> {
+    assert!(k >= 2, "k-fold cross-validation needs at least 2 folds");
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut folds = vec![Vec::new(); k];
+    let mut fold = 0;
+    for mut class in indices_by_class::<D>() {
+        class.shuffle(&mut rng);
+        for index in class {
+            folds[fold].push(index);
+            fold = (fold + 1) % k;
+        }
+    }
+    folds.iter_mut().for_each(|fold| fold.sort_unstable());
+    KFold {
+        folds,
+        next: 0,
+        data_set: PhantomData,
+    }
+}
+
+/// Yields `(training, validation)` for each fold, where the fold is the validation subset and the other folds are the training subset.
+/// see [k_fold]
+pub struct KFold<D> {
+    folds: Vec<Vec<usize>>,
+    next: usize,
+    data_set: PhantomData<fn() -> D>,
+}
+impl<D: DataSet> Iterator for KFold<D> {
+    type Item = (Subset<D>, Subset<D>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let validation = self.folds.get(self.next)?.clone();
+        let mut training = self
+            .folds
+            .iter()
+            .enumerate()
+            .filter(|&(fold, _)| fold != self.next)
+            .flat_map(|(_, indices)| indices.iter().copied())
+            .collect::<Vec<_>>();
+        training.sort_unstable();
+        self.next += 1;
+        Some((
+            Subset::new_unchecked(training),
+            Subset::new_unchecked(validation),
+        ))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.folds.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+impl<D: DataSet> ExactSizeIterator for KFold<D> {}
+impl<D> fmt::Debug for KFold<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KFold")
+            .field("folds", &self.folds)
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ClassSet, DataSet, DigitClass, Image, Label, SourceData,
+        files::Training,
+        subset::Subset,
+        test_util::{class_counts, in_memory_source},
+        validation::{
+            Count, Fraction, Holdout, KeepFirst, TrainingPart, ValidationPart, k_fold,
+            stratified_split,
+        },
+    };
+
+    in_memory_source!(
+        /// One example of each class
+        TenDigits,
+        |_| 1
+    );
+    type Data = SourceData<TenDigits, Training>;
+
+    in_memory_source!(
+        /// `2 * (c + 1)` examples of each class `c`
+        Unbalanced,
+        |class| 2 * (class.index() + 1)
+    );
+    type UnbalancedData = SourceData<Unbalanced, Training>;
+
+    fn assert_partition(training: &Subset<UnbalancedData>, validation: &Subset<UnbalancedData>) {
+        let mut indices = [training.indices(), validation.indices()].concat();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..UnbalancedData::count()).collect::<Vec<_>>());
+    }
+
+    fn classes<D: DataSet>() -> Vec<usize>
+    where
+        D::Label: Label<Class = DigitClass>,
+    {
+        D::labels().map(|label| label.class().index()).collect()
+    }
+
+    #[test]
+    fn parts_are_disjoint_data_sets() {
+        TenDigits::install();
+
+        type Training3 = TrainingPart<Data, Count<3>>;
+        type Validation3 = ValidationPart<Data, Count<3>>;
+        assert_eq!((Training3::count(), Validation3::count()), (7, 3));
+        assert_eq!(classes::<Training3>(), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(classes::<Validation3>(), [7, 8, 9]);
+        assert!(Validation3::get(3).is_none());
+        let (image, label) = Validation3::get(0).unwrap();
+        assert_eq!(
+            (image.as_bytes()[0], label.digit_class()),
+            (7, DigitClass::Seven)
+        );
+
+        assert_eq!(classes::<ValidationPart<Data, Fraction<1, 4>>>(), [8, 9]);
+        assert_eq!(
+            Fraction::<{ usize::MAX - 1 }, { usize::MAX }>::validation_count(usize::MAX),
+            usize::MAX - 1
+        );
+        assert_eq!(classes::<TrainingPart<Data, KeepFirst<2>>>(), [0, 1]);
+        assert_eq!(ValidationPart::<Data, KeepFirst<20>>::count(), 0);
+        assert_eq!(TrainingPart::<Data, Count<20>>::count(), 0);
+        assert_eq!(ValidationPart::<Data, Count<20>>::count(), 10);
+    }
+
+    #[test]
+    fn stratified_split_keeps_class_proportions() {
+        Unbalanced::install();
+        let (training, validation) = stratified_split::<UnbalancedData>(0.5, 7);
+        assert_partition(&training, &validation);
+        assert_eq!(class_counts(&validation), (1..=10).collect::<Vec<_>>());
+        assert_eq!(class_counts(&training), (1..=10).collect::<Vec<_>>());
+        assert_eq!(
+            stratified_split::<UnbalancedData>(0.5, 7),
+            (training.clone(), validation.clone())
+        );
+        assert_ne!(stratified_split::<UnbalancedData>(0.5, 8).1, validation);
+    }
+
+    #[test]
+    fn k_fold_spreads_classes_evenly() {
+        Unbalanced::install();
+        let folds = k_fold::<UnbalancedData>(5, 7).collect::<Vec<_>>();
+        assert_eq!(folds.len(), 5);
+        let mut validations = Vec::new();
+        for (training, validation) in &folds {
+            assert_partition(training, validation);
+            assert_eq!(validation.len(), 22);
+            for (class, count) in class_counts(validation).into_iter().enumerate() {
+                let per_fold = 2 * (class + 1) / 5;
+                assert!(count == per_fold || count == per_fold + 1);
+            }
+            validations.extend_from_slice(validation.indices());
+        }
+        validations.sort_unstable();
+        assert_eq!(
+            validations,
+            (0..UnbalancedData::count()).collect::<Vec<_>>()
+        );
+        assert_eq!(k_fold::<UnbalancedData>(5, 7).collect::<Vec<_>>(), folds);
+    }
+}
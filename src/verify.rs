@@ -0,0 +1,212 @@
+//! Checks dataset files against the SHA-256 digests of the files of the original distributions
+
+use crate::{
+    Files, MnistError,
+    files::{FileKind, Source},
+};
+
+use sha2::{Digest, Sha256};
+
+/// A file of a dataset with a known SHA-256 digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KnownFile {
+    /// see [Source::NAME]
+    pub dataset: &'static str,
+    pub kind: FileKind,
+    /// The lower case hexadecimal digest of the uncompressed file
+    pub sha256: &'static str,
+}
+
+/// The files whose digests are known. These are only the two MNIST labels files in `dataset/`, so [Files::verify]
+/// rejects every other file with [MnistError::UnknownFile]. Use [Files::verify_with] to check against other digests
+pub const KNOWN_FILES: &[KnownFile] = &[
+    KnownFile {
+        dataset: "MNIST",
+        kind: FileKind::TrainingLabels,
+        sha256: "65a50cbbf4e906d70832878ad85ccda5333a97f0f4c3dd2ef09a8a9eef7101c5",
+    },
+    KnownFile {
+        dataset: "MNIST",
+        kind: FileKind::TestLabels,
+        sha256: "ff7bcfd416de33731a308c3f266cc351222c34898ecbeaf847f06e48f7ec33f2",
+    },
+];
+
+/// The lower case hexadecimal SHA-256 digest of `bytes`
+pub fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Which file of [KNOWN_FILES] `bytes` is, if any
+pub fn identify(bytes: &[u8]) -> Option<&'static KnownFile> {
+    identify_in(KNOWN_FILES, bytes)
+}
+
+/// Which file of `known` `bytes` is, if any
+pub fn identify_in<'a>(known: &'a [KnownFile], bytes: &[u8]) -> Option<&'a KnownFile> {
+    let digest = sha256(bytes);
+    known.iter().find(|file| file.sha256 == digest)
+}
+
+impl<S: Source> Files<S> {
+    /// [Files::verify_with] the [KNOWN_FILES]
+    pub fn verify(&self) -> Result<[&'static KnownFile; 4], MnistError> {
+        self.verify_with(KNOWN_FILES)
+    }
+
+    /// Checks the digest of each file as it is stored on disk, see [Source::encode].
+    ///
+    /// Fails if a file is a different file of `known`, if `known` has a different digest for it,
+    /// or with [MnistError::UnknownFile] if `known` has no digest for it. Returns the matching file of `known` for each [FileKind]
+    pub fn verify_with<'a>(
+        &self,
+        known: &'a [KnownFile],
+    ) -> Result<[&'a KnownFile; 4], MnistError> {
+        let mut verified = [None; 4];
+        for kind in FileKind::ALL {
+            let digest = sha256(&S::encode(kind, self.file(kind)));
+            let mut matching = known.iter().filter(|file| file.sha256 == digest);
+            let expected = |file: &&KnownFile| file.dataset == S::NAME && file.kind == kind;
+            verified[kind as usize] = match matching.clone().find(expected) {
+                Some(file) => Some(file),
+                None => match matching.next() {
+                    Some(actual) => {
+                        return Err(MnistError::WrongFile {
+                            expected_dataset: S::NAME,
+                            expected_kind: kind,
+                            actual_dataset: actual.dataset,
+                            actual_kind: actual.kind,
+                        });
+                    }
+                    None if known.iter().any(|file| expected(&file)) => {
+                        return Err(MnistError::ChecksumMismatch {
+                            dataset: S::NAME,
+                            kind,
+                        });
+                    }
+                    None => None,
+                },
+            };
+        }
+        // files that don't match are reported before files that can't be checked
+        if let Some(kind) = FileKind::ALL
+            .into_iter()
+            .find(|&kind| verified[kind as usize].is_none())
+        {
+            return Err(MnistError::UnknownFile {
+                dataset: S::NAME,
+                kind,
+            });
+        }
+        Ok(verified.map(|file| file.expect("every file has a known digest")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        IMAGE_SIZE, MnistError, MnistFiles,
+        files::FileKind,
+        idx,
+        verify::{KNOWN_FILES, KnownFile, identify, sha256},
+    };
+
+    #[test]
+    fn identifies_mnist_labels() {
+        let directory = MnistFiles::default_directory();
+        let labels = std::fs::read(directory.join("t10k-labels.idx1-ubyte")).unwrap();
+        let known = identify(&labels).unwrap();
+        assert_eq!((known.dataset, known.kind), ("MNIST", FileKind::TestLabels));
+    }
+
+    #[test]
+    fn refuses_wrong_files() {
+        let directory = MnistFiles::default_directory();
+        let training_labels = std::fs::read(directory.join("train-labels.idx1-ubyte")).unwrap();
+        let test_labels = std::fs::read(directory.join("t10k-labels.idx1-ubyte")).unwrap();
+        let images = |count: usize| {
+            let mut images = Vec::new();
+            idx::write_images(&mut images, vec![[0; IMAGE_SIZE]; count]).unwrap();
+            images
+        };
+
+        let files = MnistFiles::from_bytes(
+            images(60_000),
+            training_labels.clone(),
+            images(10_000),
+            test_labels.clone(),
+        )
+        .unwrap();
+        assert!(matches!(
+            files.verify(),
+            Err(MnistError::UnknownFile {
+                dataset: "MNIST",
+                kind: FileKind::TrainingImages
+            })
+        ));
+        let known_images = |kind, count| KnownFile {
+            dataset: "MNIST",
+            kind,
+            sha256: sha256(&images(count)).leak(),
+        };
+        let known = [
+            known_images(FileKind::TrainingImages, 60_000),
+            KNOWN_FILES[0],
+            known_images(FileKind::TestImages, 10_000),
+            KNOWN_FILES[1],
+        ];
+        assert_eq!(
+            files.verify_with(&known).unwrap(),
+            [&known[0], &known[1], &known[2], &known[3]]
+        );
+        let mut tampered_images = images(60_000);
+        tampered_images[1000] = 1;
+        let files = MnistFiles::from_bytes(
+            tampered_images,
+            training_labels.clone(),
+            images(10_000),
+            test_labels.clone(),
+        )
+        .unwrap();
+        assert!(matches!(
+            files.verify_with(&known),
+            Err(MnistError::ChecksumMismatch {
+                dataset: "MNIST",
+                kind: FileKind::TrainingImages
+            })
+        ));
+
+        let mut tampered = test_labels.clone();
+        tampered[8] = (tampered[8] + 1) % 10;
+        let files = MnistFiles::from_bytes(
+            images(60_000),
+            training_labels.clone(),
+            images(10_000),
+            tampered,
+        )
+        .unwrap();
+        assert!(matches!(
+            files.verify(),
+            Err(MnistError::ChecksumMismatch {
+                dataset: "MNIST",
+                kind: FileKind::TestLabels
+            })
+        ));
+
+        let swapped =
+            MnistFiles::from_bytes(images(10_000), test_labels, images(60_000), training_labels)
+                .unwrap();
+        assert!(matches!(
+            swapped.verify(),
+            Err(MnistError::WrongFile {
+                expected_dataset: "MNIST",
+                expected_kind: FileKind::TrainingLabels,
+                actual_dataset: "MNIST",
+                actual_kind: FileKind::TestLabels
+            })
+        ));
+    }
+}
@@ -1,26 +1,28 @@
-use crate::{Image, image::IMAGE_WIDTH};
+use crate::GrayImage28;
 
-pub fn to_ascii_art(image: impl Image) -> String {
+/// `image` is an [Image](crate::Image) handle or a [GrayImage28]
+pub fn to_ascii_art(image: impl Into<GrayImage28>) -> String {
     to_string(image, String::new(), |&b| {
         if b >= 230 { "@" } else { "." }.into()
     })
 }
 
-pub fn to_pgm(image: impl Image) -> String {
+/// `image` is an [Image](crate::Image) handle or a [GrayImage28]
+pub fn to_pgm(image: impl Into<GrayImage28>) -> String {
     const PGM_HEADER: &str = "P2\n28 28\n255\n";
     to_string(image, PGM_HEADER.into(), |&b| format!("{}\n", b))
 }
 
 fn to_string(
-    image: impl Image,
+    image: impl Into<GrayImage28>,
     initial_string: String,
     byte_conversion_function: impl Copy + FnMut(&u8) -> String,
 ) -> String {
     image
-        .as_bytes()
-        .chunks(IMAGE_WIDTH)
+        .into()
+        .rows()
         .flat_map(move |row| {
-            row.into_iter()
+            row.iter()
                 .map(byte_conversion_function)
                 .chain(["\n".into()])
         })